@@ -0,0 +1,850 @@
+use chrono::{DateTime, Utc};
+use log::{debug, info, trace};
+use rand::{rngs::StdRng, SeedableRng};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backtest::execution::ExecutionModel,
+    consts::DEFAULT_TICKER_BUFFER_SIZE,
+    invoke::gui::{Order, Setting},
+    middleware::ticker::{Retention, SymbolBook, Ticker, TickerStats},
+    order_type::{
+        origin::{Command, Step},
+        position::PositionState,
+        signal::{Intent, Side},
+        stops::{ExitReason, ExitRule, Watch},
+        strategy::{Context, StrategyRegistry},
+    },
+};
+
+#[derive(Debug, Clone)]
+pub struct BacktestConfig {
+    // 稼働時と同じ設定値を使用する
+    pub setting: Setting,
+    pub retention: Retention,
+    // 1注文あたりの数量、損益は価格差 × 数量 × 注文指示のvolume
+    pub size: Decimal,
+    // 判定に使用する注文ロジック、setting.strategy/order_typeで選択する
+    pub registry: StrategyRegistry,
+    // シグナルから約定までの遅延と滑り
+    pub execution: ExecutionModel,
+    // interval_random・遅延のばらつき用の乱数シード
+    pub seed: u64,
+}
+
+impl Default for BacktestConfig {
+    fn default() -> Self {
+        BacktestConfig::new(Setting::new())
+    }
+}
+
+impl BacktestConfig {
+    pub fn new(setting: Setting) -> Self {
+        BacktestConfig {
+            setting,
+            retention: Retention::count(DEFAULT_TICKER_BUFFER_SIZE),
+            size: Decimal::ONE,
+            registry: StrategyRegistry::default(),
+            execution: ExecutionModel::default(),
+            seed: 0,
+        }
+    }
+}
+
+/// 決済済みの取引
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Trade {
+    pub symbol: String,
+    pub side: Side,
+    pub entry: Decimal,
+    pub exit: Decimal,
+    pub size: Decimal,
+    pub entried_at: DateTime<Utc>,
+    pub exited_at: DateTime<Utc>,
+    pub pnl: Decimal,
+}
+
+impl Trade {
+    pub fn is_win(&self) -> bool {
+        self.pnl > Decimal::ZERO
+    }
+
+    pub fn holding_ms(&self) -> i64 {
+        self.exited_at.signed_duration_since(self.entried_at).num_milliseconds()
+    }
+
+    // 稼働時の注文履歴から取引を復元する、sizeは1注文あたりの数量
+    // 方向が不明、決済価格が記録されていない注文はNone
+    pub fn from_order(order: &Order, symbol: &str, size: Decimal) -> Option<Trade> {
+        let side = match order.side.as_str() {
+            "buy" => Side::Buy,
+            "sell" => Side::Sell,
+            _ => return None,
+        };
+        if order.exit.is_zero() {
+            return None;
+        }
+        Some(Trade {
+            symbol: symbol.to_string(),
+            side,
+            entry: order.entry,
+            exit: order.exit,
+            size,
+            entried_at: order.entried_at,
+            exited_at: order.exited_at,
+            pnl: (order.exit - order.entry) * side.sign() * size,
+        })
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct EquityPoint {
+    pub at: DateTime<Utc>,
+    pub equity: Decimal,
+}
+
+/// 取引一覧から算出する集計値
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize)]
+pub struct Summary {
+    pub trades: usize,
+    pub wins: usize,
+    pub losses: usize,
+    pub total_pnl: Decimal,
+    // 0-1
+    pub win_rate: Decimal,
+    // 確定損益ベースの最大ドローダウン (正の値)
+    pub max_drawdown: Decimal,
+}
+
+impl Summary {
+    pub fn from_trades(trades: &[Trade]) -> Self {
+        let wins = trades.iter().filter(|t| t.is_win()).count();
+        let losses = trades.iter().filter(|t| t.pnl < Decimal::ZERO).count();
+        let win_rate = if trades.is_empty() {
+            Decimal::ZERO
+        } else {
+            Decimal::from(wins) / Decimal::from(trades.len())
+        };
+
+        Summary {
+            trades: trades.len(),
+            wins,
+            losses,
+            total_pnl: trades.iter().map(|t| t.pnl).sum(),
+            win_rate,
+            max_drawdown: max_drawdown(trades.iter().map(|t| t.pnl)),
+        }
+    }
+}
+
+// 損益の累積から最大ドローダウンを計算する
+pub fn max_drawdown(pnls: impl IntoIterator<Item = Decimal>) -> Decimal {
+    let mut equity = Decimal::ZERO;
+    let mut peak = Decimal::ZERO;
+    let mut drawdown = Decimal::ZERO;
+    for pnl in pnls {
+        equity += pnl;
+        peak = peak.max(equity);
+        drawdown = drawdown.max(peak - equity);
+    }
+    drawdown
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BacktestResult {
+    pub trades: Vec<Trade>,
+    // 開始時点と決済毎の確定損益
+    pub equity: Vec<EquityPoint>,
+    pub ticks: usize,
+}
+
+impl BacktestResult {
+    pub fn summary(&self) -> Summary {
+        Summary::from_trades(&self.trades)
+    }
+}
+
+// 保有中の建玉
+#[derive(Debug, Clone)]
+struct Position {
+    id: u64,
+    symbol: String,
+    side: Side,
+    entry: Decimal,
+    entried_at: DateTime<Utc>,
+    volume: u32,
+    // EntryExitの決済監視
+    watch: Option<Watch>,
+    // 決済注文の約定待ち
+    closing: bool,
+}
+
+#[derive(Debug, Clone, Copy)]
+enum PendingAction {
+    // holdはEntryExitの約定後の待機時間
+    Open {
+        id: u64,
+        side: Side,
+        hold: Option<chrono::Duration>,
+        volume: u32,
+    },
+    // volumeがNoneの場合は全数量
+    Close {
+        id: u64,
+        volume: Option<u32>,
+    },
+}
+
+// シグナルから約定までの遅延中の注文
+#[derive(Debug, Clone)]
+struct Pending {
+    symbol: String,
+    fill_at: DateTime<Utc>,
+    action: PendingAction,
+}
+
+/// 過去tickに対してorder_typeの判定を実行し、マウス操作の代わりにbid/askで約定させる
+/// 時刻はtickのtimestampを使用し、実時間の待機は行わない
+/// 注文は ExecutionModel の遅延後に最初に届いた同一シンボルのtickで約定する
+/// 約定待ちの間は、稼働時の処理中フラグと同様に新たな判定を行わない
+/// EntryExitの建玉は稼働時と同じWatchで監視し、決済条件に達したtickで決済注文を出す
+/// 監視中も判定を続け、逆方向のシグナルは設定 (opposite) に従い予定の決済を取り消す・置き換える
+/// 建玉の状態も稼働時と同じPositionStateで管理し、建玉中の新規・建玉なしの決済は行わない
+/// Originの注文指示 (command) は稼働時と同じ手順に展開し、途転の新規は決済の約定後に出す
+pub struct Backtester {
+    config: BacktestConfig,
+    exit_rule: ExitRule,
+    state: PositionState,
+    book: SymbolBook,
+    rng: StdRng,
+    next_id: u64,
+    positions: Vec<Position>,
+    pending: Vec<Pending>,
+    trades: Vec<Trade>,
+    equity: Vec<EquityPoint>,
+    // 決済の約定後に実行する注文指示の残りの手順
+    queued: Vec<Step>,
+    // 最後に実行した注文指示のid
+    signal_id: Option<String>,
+    ticks: usize,
+}
+
+impl Backtester {
+    pub fn new(config: BacktestConfig) -> Self {
+        // 注文ロジックが参照する足を集計し、判定に必要な期間を保持する
+        let strategy = config.registry.resolve(&config.setting);
+        let retention = match strategy.as_ref().and_then(|s| s.retention(&config.setting)) {
+            Some(required) => config.retention.cover(&required),
+            None => config.retention,
+        };
+        let mut book = SymbolBook::with_retention(retention);
+        if let Some(strategy) = strategy {
+            for (interval, limit) in strategy.bar_intervals(&config.setting) {
+                book.add_bars(interval, limit);
+            }
+        }
+        let rng = StdRng::seed_from_u64(config.seed);
+        let exit_rule = config.setting.exit_rule();
+        Backtester {
+            config,
+            exit_rule,
+            state: PositionState::Flat,
+            book,
+            rng,
+            next_id: 0,
+            positions: vec![],
+            pending: vec![],
+            trades: vec![],
+            equity: vec![],
+            queued: vec![],
+            signal_id: None,
+            ticks: 0,
+        }
+    }
+
+    // 全tickを処理し、残った建玉は各シンボルの最終tickで決済する
+    pub fn run(config: BacktestConfig, ticks: impl IntoIterator<Item = Ticker>) -> BacktestResult {
+        let mut backtester = Backtester::new(config);
+        for t in ticks {
+            backtester.on_tick(t);
+        }
+        backtester.finish()
+    }
+
+    fn is_busy(&self) -> bool {
+        !self.pending.is_empty()
+    }
+
+    // CORE: 1tick分の時刻を進める
+    pub fn on_tick(&mut self, t: Ticker) {
+        let Some(now) = t.timestamp() else {
+            trace!("skip tick without timestamp: {:?}", t);
+            return;
+        };
+        if self.equity.is_empty() {
+            self.equity.push(EquityPoint {
+                at: now,
+                equity: Decimal::ZERO,
+            });
+        }
+        self.ticks += 1;
+
+        // 決済条件に達した建玉、最大保有時間を過ぎた建玉は決済注文を出す
+        // 同一シンボルのtickでは決済価格 (買いはbid、売りはask) で判定する
+        let mut due = vec![];
+        for p in self.positions.iter_mut().filter(|p| !p.closing) {
+            let Some(watch) = p.watch.as_mut() else {
+                continue;
+            };
+            let reason = if p.symbol == t.symbol {
+                watch.update(p.side.exit_price(&t), now)
+            } else {
+                watch.is_expired(now).then_some(ExitReason::MaxHold)
+            };
+            if let Some(reason) = reason {
+                trace!("exit {} by {:?} at {}", p.id, reason, now);
+                due.push(p.id);
+            }
+        }
+        for id in due {
+            self.close_order(id, None, now);
+        }
+        self.fill(&t, now);
+
+        if self.is_busy() {
+            self.book.push(t);
+            return;
+        }
+
+        let symbol = t.symbol.clone();
+        let stats = self.book.push(t);
+        let command = Self::command(&self.config, stats);
+        let intent = Self::signal(&self.config, stats);
+        let Some(last) = stats.last().cloned() else {
+            return;
+        };
+        if let Some(command) = command {
+            trace!("{} {:?} at {}", symbol, command, now);
+            self.apply_command(command, &last, now);
+        } else if let Some(intent) = intent {
+            trace!("{} {:?} at {}", symbol, intent, now);
+            self.apply(intent, &last, now);
+        } else {
+            return;
+        }
+
+        // 遅延0の注文はシグナルのtickで約定する
+        self.fill(&last, now);
+    }
+
+    // 稼働時と同じ判定関数で注文内容を決める
+    fn signal(config: &BacktestConfig, stats: &TickerStats) -> Option<Intent> {
+        let strategy = config.registry.resolve(&config.setting)?;
+        strategy.signal(&Context::new(&config.setting, stats))
+    }
+
+    fn command(config: &BacktestConfig, stats: &TickerStats) -> Option<Command> {
+        let strategy = config.registry.resolve(&config.setting)?;
+        strategy.command(&Context::new(&config.setting, stats))
+    }
+
+    // 稼働時と同じく建玉に合わせて手順に展開する、同じidは一度だけ実行する
+    fn apply_command(&mut self, command: Command, t: &Ticker, now: DateTime<Utc>) {
+        if command.id.is_some() && command.id == self.signal_id {
+            return;
+        }
+        let held = self.positions.iter().find(|p| !p.closing).map(|p| (p.side, p.volume));
+        match command.steps(held) {
+            Ok(steps) => {
+                if command.id.is_some() {
+                    self.signal_id = command.id;
+                }
+                self.apply_steps(steps, &t.symbol, now);
+            }
+            Err(e) => debug!("{} at {}", e, now),
+        }
+    }
+
+    // 全数量の決済後の手順は約定まで待つ
+    fn apply_steps(&mut self, steps: Vec<Step>, symbol: &str, now: DateTime<Utc>) {
+        let mut steps = steps.into_iter();
+        while let Some(step) = steps.next() {
+            match step {
+                Step::Exit { side, volume } => {
+                    let target = self
+                        .positions
+                        .iter()
+                        .find(|p| !p.closing && p.symbol == symbol && side.is_none_or(|s| s == p.side))
+                        .map(|p| p.id);
+                    let Some(id) = target else {
+                        debug!("no position to exit {:?} at {}", side, now);
+                        return;
+                    };
+                    self.close_order(id, volume, now);
+                    if volume.is_none() {
+                        self.queued = steps.collect();
+                        return;
+                    }
+                }
+                Step::Entry { side, volume, hold_ms } => {
+                    if let Err(e) = self.state.check(Intent::Entry(side)) {
+                        debug!("{} at {}", e, now);
+                        return;
+                    }
+                    let hold = hold_ms.map(|ms| chrono::Duration::milliseconds(ms as i64));
+                    self.open_order(side, symbol, now, hold, volume);
+                }
+            }
+        }
+    }
+
+    fn apply(&mut self, intent: Intent, t: &Ticker, now: DateTime<Utc>) {
+        if let Err(e) = self.state.check(intent) {
+            debug!("{} at {}", e, now);
+            if let Intent::Entry(side) | Intent::EntryExit(side) = intent {
+                self.opposite(side, now);
+            }
+            return;
+        }
+        match intent {
+            Intent::Entry(side) => {
+                self.open_order(side, &t.symbol, now, None, 1);
+            }
+            Intent::EntryExit(side) => {
+                let hold_ms = self.config.setting.sleep_ms_with(&mut self.rng);
+                let hold = chrono::Duration::milliseconds(hold_ms as i64);
+                self.open_order(side, &t.symbol, now, Some(hold), 1);
+            }
+            Intent::Exit(side) => {
+                let targets: Vec<u64> = self
+                    .positions
+                    .iter()
+                    .filter(|p| !p.closing && p.symbol == t.symbol && side.is_none_or(|s| s == p.side))
+                    .map(|p| p.id)
+                    .collect();
+                for id in targets {
+                    self.close_order(id, None, now);
+                }
+            }
+        }
+    }
+
+    // 決済待ちの建玉と逆方向のシグナル、稼働時と同じく次の判定で決済注文を出す
+    fn opposite(&mut self, side: Side, now: DateTime<Utc>) {
+        let policy = self.config.setting.opposite;
+        let requested: Vec<u64> = self
+            .positions
+            .iter_mut()
+            .filter(|p| !p.closing)
+            .filter_map(|p| p.watch.as_mut().is_some_and(|w| w.request(side, policy)).then_some(p.id))
+            .collect();
+        for id in requested {
+            trace!("exit {} by {:?} at {}", id, ExitReason::Opposite, now);
+            self.close_order(id, None, now);
+        }
+    }
+
+    fn open_order(
+        &mut self,
+        side: Side,
+        symbol: &str,
+        now: DateTime<Utc>,
+        hold: Option<chrono::Duration>,
+        volume: u32,
+    ) -> u64 {
+        let _ = self.state.open(side);
+        let id = self.next_id;
+        self.next_id += 1;
+        let fill_at = now + self.config.execution.delay(&mut self.rng);
+        self.pending.push(Pending {
+            symbol: symbol.to_string(),
+            fill_at,
+            action: PendingAction::Open { id, side, hold, volume },
+        });
+        id
+    }
+
+    fn close_order(&mut self, id: u64, volume: Option<u32>, now: DateTime<Utc>) {
+        let Some(p) = self.positions.iter_mut().find(|p| p.id == id) else {
+            return;
+        };
+        // 一部決済は建玉の状態を変えない
+        let partial = volume.is_some_and(|v| v < p.volume);
+        if !partial {
+            if let Err(e) = self.state.exit(Some(p.side)) {
+                debug!("{} at {}", e, now);
+                return;
+            }
+        }
+        p.closing = true;
+        let symbol = p.symbol.clone();
+        let fill_at = now + self.config.execution.delay(&mut self.rng);
+        self.pending.push(Pending {
+            symbol,
+            fill_at,
+            action: PendingAction::Close { id, volume },
+        });
+    }
+
+    // 約定時刻を過ぎた同一シンボルの注文をtickの価格で約定させる
+    // 決済に続く途転の新規注文も遅延0であれば同じtickで約定する
+    fn fill(&mut self, t: &Ticker, now: DateTime<Utc>) {
+        loop {
+            let (ready, waiting): (Vec<Pending>, Vec<Pending>) = std::mem::take(&mut self.pending)
+                .into_iter()
+                .partition(|o| o.symbol == t.symbol && o.fill_at <= now);
+            self.pending = waiting;
+            if ready.is_empty() {
+                return;
+            }
+            self.fill_orders(ready, t, now);
+        }
+    }
+
+    fn fill_orders(&mut self, ready: Vec<Pending>, t: &Ticker, now: DateTime<Utc>) {
+        for order in ready {
+            match order.action {
+                PendingAction::Open { id, side, hold, volume } => {
+                    let entry = self.config.execution.entry_price(side, t);
+                    self.positions.push(Position {
+                        id,
+                        symbol: t.symbol.clone(),
+                        side,
+                        entry,
+                        entried_at: now,
+                        volume,
+                        watch: hold.map(|hold| Watch::new(&t.symbol, side, entry, now, hold, self.exit_rule)),
+                        closing: false,
+                    });
+                }
+                PendingAction::Close { id, volume } => self.close(id, volume, t, now),
+            }
+        }
+    }
+
+    // 指定した建玉をtickの価格で決済する、volumeが保有数量未満の場合は一部のみ
+    fn close(&mut self, id: u64, volume: Option<u32>, t: &Ticker, now: DateTime<Utc>) {
+        let Some(i) = self.positions.iter().position(|p| p.id == id) else {
+            return;
+        };
+        let partial = volume.filter(|v| *v < self.positions[i].volume);
+        let p = match partial {
+            Some(volume) => {
+                let held = &mut self.positions[i];
+                held.volume -= volume;
+                held.closing = false;
+                Position { volume, ..held.clone() }
+            }
+            None => {
+                let _ = self.state.closed();
+                self.positions.remove(i)
+            }
+        };
+        let exit = self.config.execution.exit_price(p.side, t);
+        let size = self.config.size * Decimal::from(p.volume);
+        let pnl = (exit - p.entry) * p.side.sign() * size;
+        self.trades.push(Trade {
+            symbol: p.symbol,
+            side: p.side,
+            entry: p.entry,
+            exit,
+            size,
+            entried_at: p.entried_at,
+            exited_at: now,
+            pnl,
+        });
+
+        let equity = self.equity.last().map_or(Decimal::ZERO, |e| e.equity) + pnl;
+        self.equity.push(EquityPoint { at: now, equity });
+        if partial.is_some() {
+            return;
+        }
+
+        // 逆方向のシグナルで置き換えた決済は、続けて逆方向のEntryExitを出す
+        if let Some(side) = p.watch.and_then(|w| w.reverse) {
+            let hold_ms = self.config.setting.sleep_ms_with(&mut self.rng);
+            let hold = chrono::Duration::milliseconds(hold_ms as i64);
+            self.open_order(side, &t.symbol, now, Some(hold), 1);
+        }
+        // 注文指示の残りの手順
+        if !self.queued.is_empty() {
+            let steps = std::mem::take(&mut self.queued);
+            self.apply_steps(steps, &t.symbol, now);
+        }
+    }
+
+    pub fn finish(mut self) -> BacktestResult {
+        // 約定前の新規注文は破棄し、建玉は各シンボルの最終tickで決済する
+        self.pending.clear();
+        let ids: Vec<(u64, String)> = self.positions.iter().map(|p| (p.id, p.symbol.clone())).collect();
+        for (id, symbol) in ids {
+            let Some(last) = self.book.get(&symbol).and_then(|s| s.last()).cloned() else {
+                continue;
+            };
+            let Some(now) = last.timestamp() else {
+                continue;
+            };
+            self.close(id, None, &last, now);
+        }
+
+        let result = BacktestResult {
+            trades: self.trades,
+            equity: self.equity,
+            ticks: self.ticks,
+        };
+        let summary = result.summary();
+        info!(
+            "backtest finished: {} ticks, {} trades, pnl {}, win rate {}, max drawdown {}",
+            result.ticks, summary.trades, summary.total_pnl, summary.win_rate, summary.max_drawdown
+        );
+        result
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::invoke::gui::{OppositeSignal, OrderType};
+    use crate::order_type::origin::Action;
+    use chrono::TimeZone;
+    use std::str::FromStr;
+
+    fn base() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()
+    }
+
+    // bid = price, ask = price + 0.002
+    fn tick(ms: i64, price: &str) -> Ticker {
+        let bid = Decimal::from_str(price).unwrap();
+        Ticker {
+            symbol: "USDJPY".to_string(),
+            bid,
+            ask: bid + Decimal::new(2, 3),
+            recived_at: Some(base() + chrono::Duration::milliseconds(ms)),
+            ..Default::default()
+        }
+    }
+
+    // 0-450msは150.000、500msで150.200へ上昇、1000ms以降は150.300
+    fn feed() -> Vec<Ticker> {
+        (0..40)
+            .map(|i| {
+                let ms = i * 50;
+                let price = match ms {
+                    0..=450 => "150.000",
+                    500..=950 => "150.200",
+                    _ => "150.300",
+                };
+                tick(ms, price)
+            })
+            .collect()
+    }
+
+    fn config(order_type: OrderType) -> BacktestConfig {
+        let mut setting = Setting::new();
+        setting.order_type = order_type;
+        setting.interval = 1;
+        BacktestConfig::new(setting)
+    }
+
+    #[test]
+    fn test_backtest_simple_entry_exit() {
+        let result = Backtester::run(config(OrderType::Simple), feed());
+        assert_eq!(result.ticks, 40);
+        assert_eq!(result.trades.len(), 1);
+
+        // 500msに買い (ask)、1秒待機後の1500msに決済 (bid)
+        let trade = &result.trades[0];
+        assert_eq!(trade.side, Side::Buy);
+        assert_eq!(trade.entry, Decimal::from_str("150.202").unwrap());
+        assert_eq!(trade.exit, Decimal::from_str("150.300").unwrap());
+        assert_eq!(trade.holding_ms(), 1_000);
+        assert_eq!(trade.pnl, Decimal::from_str("0.098").unwrap());
+
+        let summary = result.summary();
+        assert_eq!(summary.win_rate, Decimal::ONE);
+        assert_eq!(result.equity.len(), 2);
+        assert_eq!(result.equity[1].equity, Decimal::from_str("0.098").unwrap());
+    }
+
+    #[test]
+    fn test_backtest_entry_closes_at_end() {
+        // 売りエントリーのみのため上昇では約定しない
+        let result = Backtester::run(config(OrderType::SellEntry), feed());
+        assert!(result.trades.is_empty());
+
+        // 500msに買い、建玉中のため550/600msのシグナルでは追加せず、最終tickで決済
+        let result = Backtester::run(config(OrderType::BuyEntry), feed());
+        assert_eq!(result.trades.len(), 1);
+        assert_eq!(result.trades[0].entried_at, base() + chrono::Duration::milliseconds(500));
+        assert_eq!(result.trades[0].exit, Decimal::from_str("150.300").unwrap());
+    }
+
+    #[test]
+    fn test_backtest_origin_flags() {
+        let mut ticks = vec![tick(0, "150.000"), tick(100, "150.100"), tick(200, "150.050")];
+        ticks[0].flag = Some(2); // EntrySell
+        ticks[1].flag = Some(5); // ExitBuy: 売り建玉は対象外
+        ticks[2].flag = Some(6); // ExitSell
+
+        let result = Backtester::run(config(OrderType::Custom), ticks);
+        assert_eq!(result.trades.len(), 1);
+        let trade = &result.trades[0];
+        assert_eq!(trade.side, Side::Sell);
+        assert_eq!(trade.entry, Decimal::from_str("150.000").unwrap());
+        assert_eq!(trade.exit, Decimal::from_str("150.052").unwrap());
+        assert_eq!(trade.pnl, Decimal::from_str("-0.052").unwrap());
+        assert_eq!(trade.exited_at, base() + chrono::Duration::milliseconds(200));
+    }
+
+    #[test]
+    fn test_backtest_execution_latency() {
+        // 300ms遅れて約定するため、500msのシグナルは800msの価格 (150.200) で買う
+        // 決済は約定の1秒後の1800msに発注し2100msに約定予定だが、データは1950msまでのため最終tickで決済
+        let mut delayed = config(OrderType::Simple);
+        delayed.execution = ExecutionModel::new(300).slippage(Decimal::new(1, 3));
+        let result = Backtester::run(delayed, feed());
+        assert_eq!(result.trades.len(), 1);
+
+        let trade = &result.trades[0];
+        assert_eq!(trade.entried_at, base() + chrono::Duration::milliseconds(800));
+        assert_eq!(trade.entry, Decimal::from_str("150.203").unwrap());
+        assert_eq!(trade.exited_at, base() + chrono::Duration::milliseconds(1950));
+        assert_eq!(trade.exit, Decimal::from_str("150.299").unwrap());
+
+        // 遅延なしより成績が悪化する
+        let ideal = Backtester::run(config(OrderType::Simple), feed());
+        assert!(trade.pnl < ideal.trades[0].pnl);
+    }
+
+    #[test]
+    fn test_backtest_take_profit_and_stop_loss() {
+        // 500msに150.202で買い、1000msのbid 150.300で9.8pipsの利益
+        let mut tp = config(OrderType::Simple);
+        tp.setting.take_profit = "5".to_string();
+        let result = Backtester::run(tp, feed());
+        let trade = &result.trades[0];
+        assert_eq!(trade.exited_at, base() + chrono::Duration::milliseconds(1_000));
+        assert_eq!(trade.exit, Decimal::from_str("150.300").unwrap());
+
+        // 届かない利確幅では最大保有時間で決済する
+        let mut far = config(OrderType::Simple);
+        far.setting.take_profit = "20".to_string();
+        assert_eq!(Backtester::run(far, feed()).trades[0].holding_ms(), 1_000);
+
+        // 600msに150.150へ下落し、5.2pipsの損失で損切り
+        let ticks: Vec<Ticker> = feed()
+            .into_iter()
+            .enumerate()
+            .map(|(i, t)| if (12..20).contains(&i) { tick(i as i64 * 50, "150.150") } else { t })
+            .collect();
+        let mut sl = config(OrderType::Simple);
+        sl.setting.stop_loss = "5".to_string();
+        let trade = &Backtester::run(sl, ticks).trades[0];
+        assert_eq!(trade.exited_at, base() + chrono::Duration::milliseconds(600));
+        assert_eq!(trade.pnl, Decimal::from_str("-0.052").unwrap());
+    }
+
+    #[test]
+    fn test_backtest_trailing_stop() {
+        // 1000msの150.300から1200msに150.240へ戻すと、5pipsのトレーリング (150.250) で決済
+        let ticks: Vec<Ticker> = feed()
+            .into_iter()
+            .enumerate()
+            .map(|(i, t)| if i >= 24 { tick(i as i64 * 50, "150.240") } else { t })
+            .collect();
+        let mut trailing = config(OrderType::Simple);
+        trailing.setting.trailing_stop = "5".to_string();
+        let trade = &Backtester::run(trailing, ticks.clone()).trades[0];
+        assert_eq!(trade.exited_at, base() + chrono::Duration::milliseconds(1_200));
+        assert_eq!(trade.exit, Decimal::from_str("150.240").unwrap());
+
+        // 建値移動のみでは戻りが建値に届かないため最大保有時間で決済
+        let mut break_even = config(OrderType::Simple);
+        break_even.setting.break_even = "5".to_string();
+        assert_eq!(Backtester::run(break_even, ticks).trades[0].holding_ms(), 1_000);
+    }
+
+    #[test]
+    fn test_backtest_opposite_signal() {
+        // 500msの買いの後、700msに149.900へ急落して売りシグナル
+        let ticks: Vec<Ticker> = feed()
+            .into_iter()
+            .enumerate()
+            .map(|(i, t)| if i >= 14 { tick(i as i64 * 50, "149.900") } else { t })
+            .collect();
+        let run = |policy| {
+            let mut c = config(OrderType::Simple);
+            c.setting.opposite = policy;
+            Backtester::run(c, ticks.clone())
+        };
+
+        // Ignore: 予定通り最大保有時間で決済
+        let ignore = run(OppositeSignal::Ignore);
+        assert_eq!(ignore.trades[0].holding_ms(), 1_000);
+
+        // Cancel: 売りシグナルで即時決済し、途転はしない
+        let cancel = run(OppositeSignal::Cancel);
+        let trade = &cancel.trades[0];
+        assert_eq!(trade.exited_at, base() + chrono::Duration::milliseconds(700));
+        assert_eq!(trade.exit, Decimal::from_str("149.900").unwrap());
+        assert!(cancel.trades.iter().skip(1).all(|t| t.entried_at > trade.exited_at));
+
+        // Replace: 決済と同じtickで売りの新規
+        let replace = run(OppositeSignal::Replace);
+        assert_eq!(replace.trades[0].exited_at, trade.exited_at);
+        let reverse = &replace.trades[1];
+        assert_eq!(reverse.side, Side::Sell);
+        assert_eq!(reverse.entried_at, trade.exited_at);
+        assert_eq!(reverse.entry, Decimal::from_str("149.900").unwrap());
+    }
+
+    #[test]
+    fn test_backtest_origin_commands() {
+        let command = |action, side, volume, id: &str| Command {
+            action,
+            side,
+            volume,
+            hold_ms: None,
+            id: Some(id.to_string()),
+        };
+        let mut ticks: Vec<Ticker> = (0..6).map(|i| tick(i * 100, "150.000")).collect();
+        ticks[1] = tick(100, "150.100");
+        ticks[3] = tick(300, "150.200");
+        ticks[0].command = Some(command(Action::Entry, Some(Side::Buy), Some(3), "a"));
+        // 同じidの再送は実行しない
+        ticks[1].command = Some(command(Action::Entry, Some(Side::Buy), Some(3), "a"));
+        ticks[1].flag = Some(6);
+        ticks[2].command = Some(command(Action::PartialClose, None, Some(1), "b"));
+        ticks[3].command = Some(command(Action::Reverse, None, None, "c"));
+        ticks[5].command = Some(command(Action::CloseAll, None, None, "d"));
+
+        let result = Backtester::run(config(OrderType::Custom), ticks);
+        let summary: Vec<(Side, Decimal, i64)> = result
+            .trades
+            .iter()
+            .map(|t| (t.side, t.size, (t.exited_at - base()).num_milliseconds()))
+            .collect();
+        assert_eq!(
+            summary,
+            vec![
+                (Side::Buy, Decimal::from(1), 200),
+                (Side::Buy, Decimal::from(2), 300),
+                (Side::Sell, Decimal::from(2), 500),
+            ]
+        );
+        // 150.002で買い、一部を150.000、残りを150.200で決済
+        assert_eq!(result.trades[1].pnl, Decimal::from_str("0.396").unwrap());
+        assert_eq!(result.trades[2].entry, Decimal::from_str("150.200").unwrap());
+    }
+
+    #[test]
+    fn test_max_drawdown() {
+        let pnls = [1, -2, 3, -1, -3, 2].map(Decimal::from);
+        // 累積: 1, -1, 2, 1, -2, 0 → 高値2から-2まで
+        assert_eq!(max_drawdown(pnls), Decimal::from(4));
+        assert_eq!(max_drawdown([]), Decimal::ZERO);
+        assert_eq!(Summary::from_trades(&[]), Summary::default());
+    }
+}
@@ -0,0 +1,129 @@
+use rand::Rng;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    middleware::{synthetic::standard_normal, ticker::Ticker},
+    order_type::signal::Side,
+};
+
+/// 遅延のばらつき (ms)
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize)]
+pub enum Jitter {
+    #[default]
+    None,
+    // 0..=max_ms の一様分布
+    Uniform { max_ms: u64 },
+    // 平均0、標準偏差std_msの正規分布 (負の遅延は0に丸める)
+    Normal { std_ms: f64 },
+    // 平均mean_msの指数分布、まれに大きく遅れる経路を表す
+    Exponential { mean_ms: f64 },
+}
+
+impl Jitter {
+    pub fn sample<R: Rng>(&self, rng: &mut R) -> i64 {
+        match *self {
+            Jitter::None => 0,
+            Jitter::Uniform { max_ms } => rng.gen_range(0..=max_ms) as i64,
+            Jitter::Normal { std_ms } => (standard_normal(rng) * std_ms).round() as i64,
+            Jitter::Exponential { mean_ms } => {
+                let u: f64 = rng.gen_range(f64::EPSILON..1.0);
+                (-mean_ms * u.ln()).round() as i64
+            }
+        }
+    }
+}
+
+/// シグナルからクリックによる約定までの遅延と滑りのモデル
+/// 実際の約定はOrderDispatcherの待ち行列、lock、マウス移動、ブローカーとの往復を経た後になる
+/// 遅延後に最初に届いたtickの価格で約定し、さらにslippage分だけ不利な価格とする
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize)]
+pub struct ExecutionModel {
+    pub latency_ms: u64,
+    pub jitter: Jitter,
+    // 1約定あたりの不利な価格差
+    pub slippage: Decimal,
+}
+
+impl ExecutionModel {
+    pub fn new(latency_ms: u64) -> Self {
+        ExecutionModel {
+            latency_ms,
+            ..Default::default()
+        }
+    }
+
+    pub fn jitter(mut self, jitter: Jitter) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn slippage(mut self, slippage: Decimal) -> Self {
+        self.slippage = slippage;
+        self
+    }
+
+    // 1回の注文の遅延
+    pub fn delay<R: Rng>(&self, rng: &mut R) -> chrono::Duration {
+        let ms = (self.latency_ms as i64 + self.jitter.sample(rng)).max(0);
+        chrono::Duration::milliseconds(ms)
+    }
+
+    // 新規約定価格
+    pub fn entry_price(&self, side: Side, t: &Ticker) -> Decimal {
+        side.entry_price(t) + self.slippage * side.sign()
+    }
+
+    // 決済約定価格
+    pub fn exit_price(&self, side: Side, t: &Ticker) -> Decimal {
+        side.exit_price(t) - self.slippage * side.sign()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use rand::{rngs::StdRng, SeedableRng};
+
+    #[test]
+    fn test_execution_delay() {
+        let mut rng = StdRng::seed_from_u64(1);
+        let model = ExecutionModel::new(300);
+        assert_eq!(model.delay(&mut rng), chrono::Duration::milliseconds(300));
+
+        let model = model.jitter(Jitter::Uniform { max_ms: 100 });
+        for _ in 0..100 {
+            let ms = model.delay(&mut rng).num_milliseconds();
+            assert!((300..=400).contains(&ms));
+        }
+
+        // 負の遅延にはならない
+        let model = ExecutionModel::new(0).jitter(Jitter::Normal { std_ms: 50.0 });
+        assert!((0..100).all(|_| model.delay(&mut rng).num_milliseconds() >= 0));
+    }
+
+    #[test]
+    fn test_jitter_distribution_mean() {
+        let mut rng = StdRng::seed_from_u64(7);
+        let n = 5_000;
+        let mean = |j: Jitter, rng: &mut StdRng| (0..n).map(|_| j.sample(rng)).sum::<i64>() as f64 / n as f64;
+
+        assert!(mean(Jitter::Normal { std_ms: 50.0 }, &mut rng).abs() < 5.0);
+        assert!((mean(Jitter::Exponential { mean_ms: 100.0 }, &mut rng) - 100.0).abs() < 10.0);
+        assert!((mean(Jitter::Uniform { max_ms: 100 }, &mut rng) - 50.0).abs() < 5.0);
+    }
+
+    #[test]
+    fn test_execution_slippage_is_adverse() {
+        let t = Ticker {
+            bid: Decimal::new(150_000, 3),
+            ask: Decimal::new(150_002, 3),
+            ..Default::default()
+        };
+        let model = ExecutionModel::default().slippage(Decimal::new(1, 3));
+        assert_eq!(model.entry_price(Side::Buy, &t), Decimal::new(150_003, 3));
+        assert_eq!(model.exit_price(Side::Buy, &t), Decimal::new(149_999, 3));
+        assert_eq!(model.entry_price(Side::Sell, &t), Decimal::new(149_999, 3));
+        assert_eq!(model.exit_price(Side::Sell, &t), Decimal::new(150_003, 3));
+    }
+}
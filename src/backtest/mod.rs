@@ -0,0 +1,6 @@
+/// 過去tickに対して注文判定を実行し、損益を検証するモジュール
+pub mod engine;
+pub mod execution;
+pub mod monte_carlo;
+pub mod optimize;
+pub mod report;
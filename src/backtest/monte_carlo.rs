@@ -0,0 +1,287 @@
+use log::info;
+use rand::{rngs::StdRng, seq::SliceRandom, Rng, SeedableRng};
+use rust_decimal::{prelude::FromPrimitive, Decimal};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backtest::engine::{max_drawdown, Summary, Trade},
+    consts::DEFAULT_MONTE_CARLO_RUNS,
+    error::SignalError,
+};
+
+/// 取引順序の再標本化方法
+/// Shuffle: 同じ取引を並べ替える (損益合計は変わらず、ドローダウンのみ変化する)
+/// Bootstrap: 取引を重複ありで同数抽出する
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum Resample {
+    Shuffle,
+    #[default]
+    Bootstrap,
+}
+
+/// 信頼区間
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize)]
+pub struct Interval {
+    pub lower: Decimal,
+    pub median: Decimal,
+    pub upper: Decimal,
+}
+
+impl Interval {
+    // 昇順に並んだ値から両側confidenceの区間を求める
+    fn from_sorted(values: &[Decimal], confidence: f64) -> Self {
+        let tail = (1.0 - confidence) / 2.0;
+        Interval {
+            lower: percentile(values, tail),
+            median: percentile(values, 0.5),
+            upper: percentile(values, 1.0 - tail),
+        }
+    }
+}
+
+// nearest-rank、qは0-1
+fn percentile(sorted: &[Decimal], q: f64) -> Decimal {
+    if sorted.is_empty() {
+        return Decimal::ZERO;
+    }
+    let rank = (q * sorted.len() as f64).ceil() as usize;
+    sorted[rank.clamp(1, sorted.len()) - 1]
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct MonteCarloResult {
+    pub runs: usize,
+    pub confidence: f64,
+    // 元の取引順序での集計
+    pub original: Summary,
+    pub final_pnl: Interval,
+    pub max_drawdown: Interval,
+    // 損益合計が0以下になった試行の割合 (0-1)
+    pub loss_probability: f64,
+}
+
+impl MonteCarloResult {
+    // 信頼区間の下限でも利益が残るか
+    pub fn is_robust(&self) -> bool {
+        self.final_pnl.lower > Decimal::ZERO
+    }
+}
+
+/// 取引列のモンテカルロ分析
+/// バックテストで得た取引を並べ替え・間引き・滑りの上乗せで再標本化し、偶然の好成績かを判断する
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct MonteCarlo {
+    runs: usize,
+    seed: u64,
+    resample: Resample,
+    // 各取引を見送る確率 (0-1)
+    skip_probability: f64,
+    // 1約定あたり0-slippageの一様分布で不利な価格差を上乗せする、1取引で新規・決済の2回
+    slippage: Decimal,
+    confidence: f64,
+}
+
+impl Default for MonteCarlo {
+    fn default() -> Self {
+        MonteCarlo::new(DEFAULT_MONTE_CARLO_RUNS)
+    }
+}
+
+impl MonteCarlo {
+    pub fn new(runs: usize) -> Self {
+        MonteCarlo {
+            runs,
+            seed: 0,
+            resample: Resample::Bootstrap,
+            skip_probability: 0.0,
+            slippage: Decimal::ZERO,
+            confidence: 0.95,
+        }
+    }
+
+    pub fn seed(mut self, seed: u64) -> Self {
+        self.seed = seed;
+        self
+    }
+
+    pub fn resample(mut self, resample: Resample) -> Self {
+        self.resample = resample;
+        self
+    }
+
+    pub fn skip_probability(mut self, p: f64) -> Self {
+        self.skip_probability = p;
+        self
+    }
+
+    pub fn slippage(mut self, slippage: Decimal) -> Self {
+        self.slippage = slippage;
+        self
+    }
+
+    pub fn confidence(mut self, confidence: f64) -> Self {
+        self.confidence = confidence;
+        self
+    }
+
+    // 1試行分の損益列
+    fn sample(&self, trades: &[Trade], rng: &mut StdRng) -> Vec<Decimal> {
+        let picked: Vec<&Trade> = match self.resample {
+            Resample::Shuffle => {
+                let mut picked: Vec<&Trade> = trades.iter().collect();
+                picked.shuffle(rng);
+                picked
+            }
+            Resample::Bootstrap => (0..trades.len()).map(|_| &trades[rng.gen_range(0..trades.len())]).collect(),
+        };
+
+        let mut pnls = Vec::with_capacity(picked.len());
+        for t in picked {
+            if self.skip_probability > 0.0 && rng.gen_bool(self.skip_probability) {
+                continue;
+            }
+            let slip: Decimal = (0..2)
+                .map(|_| Decimal::from_f64(rng.gen::<f64>()).unwrap_or_default() * self.slippage)
+                .sum();
+            pnls.push(t.pnl - slip * t.size);
+        }
+        pnls
+    }
+
+    // CORE: runs回の試行から損益合計と最大ドローダウンの信頼区間を求める
+    pub fn run(&self, trades: &[Trade]) -> Result<MonteCarloResult, SignalError> {
+        if self.runs == 0
+            || !(0.0..1.0).contains(&self.skip_probability)
+            || self.confidence <= 0.0
+            || self.confidence >= 1.0
+            || self.slippage < Decimal::ZERO
+        {
+            return Err(SignalError::Parse(format!("invalid monte carlo config: {:?}", self)));
+        }
+
+        let mut rng = StdRng::seed_from_u64(self.seed);
+        let mut finals = Vec::with_capacity(self.runs);
+        let mut drawdowns = Vec::with_capacity(self.runs);
+        if !trades.is_empty() {
+            for _ in 0..self.runs {
+                let pnls = self.sample(trades, &mut rng);
+                finals.push(pnls.iter().sum::<Decimal>());
+                drawdowns.push(max_drawdown(pnls));
+            }
+        }
+        finals.sort();
+        drawdowns.sort();
+
+        let losses = finals.iter().filter(|p| **p <= Decimal::ZERO).count();
+        let result = MonteCarloResult {
+            runs: finals.len(),
+            confidence: self.confidence,
+            original: Summary::from_trades(trades),
+            final_pnl: Interval::from_sorted(&finals, self.confidence),
+            max_drawdown: Interval::from_sorted(&drawdowns, self.confidence),
+            loss_probability: if finals.is_empty() { 0.0 } else { losses as f64 / finals.len() as f64 },
+        };
+        info!(
+            "monte carlo: {} runs, pnl {:?}, max drawdown {:?}, loss probability {}",
+            result.runs, result.final_pnl, result.max_drawdown, result.loss_probability
+        );
+        Ok(result)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{invoke::gui::Order, order_type::signal::Side};
+    use chrono::{TimeZone, Utc};
+
+    fn trades(pnls: &[i64]) -> Vec<Trade> {
+        let at = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        pnls.iter()
+            .map(|pnl| Trade {
+                symbol: "USDJPY".to_string(),
+                side: Side::Buy,
+                entry: Decimal::new(150_000, 3),
+                exit: Decimal::new(150_000 + pnl, 3),
+                size: Decimal::ONE,
+                entried_at: at,
+                exited_at: at,
+                pnl: Decimal::new(*pnl, 3),
+            })
+            .collect()
+    }
+
+    #[test]
+    fn test_monte_carlo_from_orders() {
+        let order = |side: &str, entry: i64, exit: Option<i64>| {
+            let mut order = Order::new(Decimal::new(entry, 3));
+            order.side = side.to_string();
+            order.done(exit.map(|exit| Decimal::new(exit, 3)))
+        };
+        let orders = [
+            order("buy", 150_000, Some(150_050)),
+            order("sell", 150_000, Some(150_020)),
+            // 決済価格のない注文は除く
+            order("buy", 150_000, None),
+            order("", 150_000, Some(150_010)),
+        ];
+        let trades: Vec<Trade> = orders
+            .iter()
+            .filter_map(|o| Trade::from_order(o, "USDJPY", Decimal::TWO))
+            .collect();
+        assert_eq!(trades.len(), 2);
+        assert_eq!(trades[0].pnl, Decimal::new(100, 3));
+        assert_eq!(trades[1].pnl, Decimal::new(-40, 3));
+
+        let result = MonteCarlo::new(100).resample(Resample::Shuffle).run(&trades).unwrap();
+        assert_eq!(result.original.total_pnl, Decimal::new(60, 3));
+    }
+
+    #[test]
+    fn test_monte_carlo_shuffle_keeps_total() {
+        let trades = trades(&[10, -20, 30, -5, 15]);
+        let result = MonteCarlo::new(200).resample(Resample::Shuffle).run(&trades).unwrap();
+        // 並べ替えのみでは損益合計は変わらない
+        assert_eq!(result.final_pnl.lower, Decimal::new(30, 3));
+        assert_eq!(result.final_pnl.upper, Decimal::new(30, 3));
+        // ドローダウンは順序で変わる、最小は損失が連続しない並び
+        assert!(result.max_drawdown.lower <= Decimal::new(20, 3));
+        assert_eq!(result.max_drawdown.upper, Decimal::new(25, 3));
+        assert_eq!(result.loss_probability, 0.0);
+        assert!(result.is_robust());
+    }
+
+    #[test]
+    fn test_monte_carlo_bootstrap_interval() {
+        // 勝ち負けが半々の取引は下限が負になる
+        let trades = trades(&[10, -10, 10, -10, 10, -10, 10, -9]);
+        let mc = MonteCarlo::new(500).seed(3);
+        let result = mc.run(&trades).unwrap();
+        assert!(result.final_pnl.lower < Decimal::ZERO);
+        assert!(result.final_pnl.upper > Decimal::ZERO);
+        assert!(result.loss_probability > 0.2);
+        assert!(!result.is_robust());
+
+        // 同じシードなら同じ結果
+        assert_eq!(mc.run(&trades).unwrap().final_pnl, result.final_pnl);
+    }
+
+    #[test]
+    fn test_monte_carlo_skip_and_slippage() {
+        let trades = trades(&[10; 10]);
+        let result = MonteCarlo::new(100)
+            .resample(Resample::Shuffle)
+            .skip_probability(0.5)
+            .slippage(Decimal::new(1, 3))
+            .run(&trades)
+            .unwrap();
+        // 見送りと滑りで元の損益合計を上回ることはない
+        assert!(result.final_pnl.upper < Decimal::new(100, 3));
+        assert!(result.final_pnl.lower >= Decimal::ZERO);
+
+        assert!(MonteCarlo::new(0).run(&trades).is_err());
+        assert!(MonteCarlo::new(10).skip_probability(1.0).run(&trades).is_err());
+        assert!(MonteCarlo::new(10).confidence(1.0).run(&trades).is_err());
+        assert_eq!(MonteCarlo::default().run(&[]).unwrap().runs, 0);
+    }
+}
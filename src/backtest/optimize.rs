@@ -0,0 +1,357 @@
+use std::path::Path;
+
+use log::info;
+use rand::{rngs::StdRng, seq::SliceRandom, SeedableRng};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backtest::engine::{BacktestConfig, Backtester, Summary, Trade},
+    error::SignalError,
+    invoke::gui::{Setting, Speed},
+    middleware::{file, ticker::Ticker},
+};
+
+/// 探索するSettingのパラメータ
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Params {
+    pub speed: Speed,
+    pub vol: String,
+    pub interval: u32,
+    pub interval_random: bool,
+}
+
+impl Params {
+    // 基準の設定にパラメータを上書きする
+    pub fn apply(&self, base: &Setting) -> Setting {
+        Setting {
+            speed: self.speed,
+            vol: self.vol.clone(),
+            interval: self.interval,
+            interval_random: self.interval_random,
+            ..base.clone()
+        }
+    }
+}
+
+/// 各パラメータの候補値、空の項目は基準の設定値を使用する
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct ParamSpace {
+    pub speeds: Vec<Speed>,
+    pub vols: Vec<String>,
+    pub intervals: Vec<u32>,
+    pub interval_randoms: Vec<bool>,
+}
+
+impl ParamSpace {
+    // 全組み合わせ
+    pub fn grid(&self, base: &Setting) -> Vec<Params> {
+        let speeds = or_base(&self.speeds, base.speed);
+        let vols = or_base(&self.vols, base.vol.clone());
+        let intervals = or_base(&self.intervals, base.interval);
+        let interval_randoms = or_base(&self.interval_randoms, base.interval_random);
+
+        let mut params = vec![];
+        for speed in speeds.iter() {
+            for vol in vols.iter() {
+                for interval in intervals.iter() {
+                    for interval_random in interval_randoms.iter() {
+                        params.push(Params {
+                            speed: *speed,
+                            vol: vol.clone(),
+                            interval: *interval,
+                            interval_random: *interval_random,
+                        });
+                    }
+                }
+            }
+        }
+        params
+    }
+}
+
+// 候補が空の場合は基準値のみとする
+fn or_base<T: Clone>(values: &[T], base: T) -> Vec<T> {
+    if values.is_empty() {
+        vec![base]
+    } else {
+        values.to_vec()
+    }
+}
+
+/// 探索方法
+/// Grid: 全組み合わせ、Random: 全組み合わせから重複なしでsamples件を抽出
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum Search {
+    #[default]
+    Grid,
+    Random { samples: usize, seed: u64 },
+}
+
+/// 順位付けの指標、いずれも値が大きいほど良い
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum Metric {
+    #[default]
+    TotalPnl,
+    WinRate,
+    // 最大ドローダウンが小さいほど良い
+    MaxDrawdown,
+    // 損益合計 / 最大ドローダウン
+    PnlToDrawdown,
+}
+
+impl Metric {
+    pub fn score(&self, summary: &Summary) -> Decimal {
+        match self {
+            Metric::TotalPnl => summary.total_pnl,
+            Metric::WinRate => summary.win_rate,
+            Metric::MaxDrawdown => -summary.max_drawdown,
+            Metric::PnlToDrawdown => {
+                if summary.max_drawdown.is_zero() {
+                    summary.total_pnl
+                } else {
+                    summary.total_pnl / summary.max_drawdown
+                }
+            }
+        }
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SweepResult {
+    pub params: Params,
+    pub summary: Summary,
+    pub score: Decimal,
+}
+
+/// ウォークフォワードの1区間
+/// 区間の前半 (in-sample) で最適化し、後半 (out-of-sample) で検証する
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Fold {
+    pub in_sample: std::ops::Range<usize>,
+    pub out_of_sample: std::ops::Range<usize>,
+    pub best: SweepResult,
+    pub validation: Summary,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct WalkForwardResult {
+    pub folds: Vec<Fold>,
+    // 全区間のout-of-sample取引を通した集計
+    pub out_of_sample: Summary,
+}
+
+pub struct Optimizer {
+    base: BacktestConfig,
+    space: ParamSpace,
+    search: Search,
+    metric: Metric,
+}
+
+impl Optimizer {
+    pub fn new(base: BacktestConfig, space: ParamSpace) -> Self {
+        Optimizer {
+            base,
+            space,
+            search: Search::Grid,
+            metric: Metric::TotalPnl,
+        }
+    }
+
+    pub fn search(mut self, search: Search) -> Self {
+        self.search = search;
+        self
+    }
+
+    pub fn metric(mut self, metric: Metric) -> Self {
+        self.metric = metric;
+        self
+    }
+
+    // 評価するパラメータの一覧
+    pub fn candidates(&self) -> Vec<Params> {
+        let mut params = self.space.grid(&self.base.setting);
+        if let Search::Random { samples, seed } = self.search {
+            let mut rng = StdRng::seed_from_u64(seed);
+            params.shuffle(&mut rng);
+            params.truncate(samples);
+        }
+        params
+    }
+
+    fn backtest(&self, params: &Params, ticks: &[Ticker]) -> Vec<Trade> {
+        let config = BacktestConfig {
+            setting: params.apply(&self.base.setting),
+            ..self.base.clone()
+        };
+        Backtester::run(config, ticks.iter().cloned()).trades
+    }
+
+    // CORE: 全候補をバックテストし、指標の降順に並べる
+    pub fn sweep(&self, ticks: &[Ticker]) -> Vec<SweepResult> {
+        let mut results: Vec<SweepResult> = self
+            .candidates()
+            .into_iter()
+            .map(|params| {
+                let summary = Summary::from_trades(&self.backtest(&params, ticks));
+                let score = self.metric.score(&summary);
+                SweepResult { params, summary, score }
+            })
+            .collect();
+        results.sort_by_key(|r| std::cmp::Reverse(r.score));
+        results
+    }
+
+    // tick列をfolds区間に分け、各区間の先頭in_sample_ratioで最適化、残りで検証する
+    pub fn walk_forward(&self, ticks: &[Ticker], folds: usize, in_sample_ratio: f64) -> Result<WalkForwardResult, SignalError> {
+        if folds == 0 || in_sample_ratio <= 0.0 || in_sample_ratio >= 1.0 {
+            return Err(SignalError::Parse(format!(
+                "invalid walk-forward split: folds {}, in_sample_ratio {}",
+                folds, in_sample_ratio
+            )));
+        }
+
+        let len = ticks.len() / folds;
+        let mut results = vec![];
+        let mut out_of_sample_trades = vec![];
+        for i in 0..folds {
+            let start = i * len;
+            let end = if i + 1 == folds { ticks.len() } else { start + len };
+            let split = start + ((end - start) as f64 * in_sample_ratio) as usize;
+
+            let Some(best) = self.sweep(&ticks[start..split]).into_iter().next() else {
+                continue;
+            };
+            let trades = self.backtest(&best.params, &ticks[split..end]);
+            let validation = Summary::from_trades(&trades);
+            info!(
+                "walk-forward fold {}: {:?} in-sample score {}, out-of-sample pnl {}",
+                i, best.params, best.score, validation.total_pnl
+            );
+            out_of_sample_trades.extend(trades);
+
+            results.push(Fold {
+                in_sample: start..split,
+                out_of_sample: split..end,
+                best,
+                validation,
+            });
+        }
+
+        Ok(WalkForwardResult {
+            folds: results,
+            out_of_sample: Summary::from_trades(&out_of_sample_trades),
+        })
+    }
+}
+
+// 最適なパラメータを適用した設定をsetting.jsonとして書き出す
+pub fn export_setting(base: &Setting, params: &Params, path: &Path) -> Result<Setting, SignalError> {
+    let setting = params.apply(base);
+    if let Some(dir) = path.parent().filter(|d| !d.as_os_str().is_empty()) {
+        std::fs::create_dir_all(dir)?;
+    }
+    file::write(&path.to_string_lossy(), &setting)?;
+    Ok(setting)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+    use std::str::FromStr;
+
+    // 1秒かけて0.1上昇し、次の1秒で0.1下落する
+    fn feed(n: i64) -> Vec<Ticker> {
+        let base = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        (0..n)
+            .map(|i| {
+                let step = if (i / 20) % 2 == 0 { i % 20 } else { 20 - i % 20 };
+                let bid = Decimal::from_str("150.000").unwrap() + Decimal::new(step * 5, 3);
+                Ticker {
+                    symbol: "USDJPY".to_string(),
+                    bid,
+                    ask: bid + Decimal::new(2, 3),
+                    recived_at: Some(base + chrono::Duration::milliseconds(i * 50)),
+                    ..Default::default()
+                }
+            })
+            .collect()
+    }
+
+    fn space() -> ParamSpace {
+        ParamSpace {
+            speeds: vec![Speed::Fast, Speed::Medium],
+            vols: vec!["0.005".to_string(), "0.05".to_string(), "1".to_string()],
+            intervals: vec![1, 2],
+            interval_randoms: vec![],
+        }
+    }
+
+    #[test]
+    fn test_param_space_grid() {
+        let base = Setting::new();
+        let grid = space().grid(&base);
+        assert_eq!(grid.len(), 12);
+        assert!(grid.iter().all(|p| p.interval_random == base.interval_random));
+        assert_eq!(ParamSpace::default().grid(&base).len(), 1);
+    }
+
+    #[test]
+    fn test_optimizer_random_search() {
+        let optimizer = Optimizer::new(BacktestConfig::default(), space()).search(Search::Random { samples: 5, seed: 1 });
+        let candidates = optimizer.candidates();
+        assert_eq!(candidates.len(), 5);
+        // 同じシードなら同じ候補
+        assert_eq!(candidates, optimizer.candidates());
+    }
+
+    #[test]
+    fn test_optimizer_sweep_ranked() {
+        let optimizer = Optimizer::new(BacktestConfig::default(), space()).metric(Metric::TotalPnl);
+        let results = optimizer.sweep(&feed(200));
+        assert_eq!(results.len(), 12);
+        assert!(results.windows(2).all(|w| w[0].score >= w[1].score));
+        // 閾値1は発注しない
+        assert!(results.iter().filter(|r| r.params.vol == "1").all(|r| r.summary.trades == 0));
+    }
+
+    #[test]
+    fn test_optimizer_walk_forward() {
+        let optimizer = Optimizer::new(BacktestConfig::default(), space());
+        let ticks = feed(400);
+        let result = optimizer.walk_forward(&ticks, 4, 0.75).unwrap();
+        assert_eq!(result.folds.len(), 4);
+        assert_eq!(result.folds[0].in_sample, 0..75);
+        assert_eq!(result.folds[0].out_of_sample, 75..100);
+        assert_eq!(result.folds[3].out_of_sample.end, 400);
+        let trades: usize = result.folds.iter().map(|f| f.validation.trades).sum();
+        assert_eq!(result.out_of_sample.trades, trades);
+
+        assert!(optimizer.walk_forward(&ticks, 0, 0.5).is_err());
+        assert!(optimizer.walk_forward(&ticks, 2, 1.0).is_err());
+    }
+
+    #[test]
+    fn test_export_setting() {
+        let dir = std::env::temp_dir().join(format!("signalrs_export_{}", std::process::id()));
+        let path = dir.join("setting.json");
+        let params = Params {
+            speed: Speed::Medium,
+            vol: "0.05".to_string(),
+            interval: 3,
+            interval_random: true,
+        };
+        let mut base = Setting::new();
+        base.tcp = "9000".to_string();
+        export_setting(&base, &params, &path).unwrap();
+
+        // GUIのset(1)と同じ形式で読み込める
+        let loaded: Setting = file::read(&path.to_string_lossy()).unwrap();
+        assert_eq!(loaded.tcp, "9000");
+        assert_eq!(loaded.speed, Speed::Medium);
+        assert_eq!(loaded.vol, "0.05");
+        assert!(loaded.interval_random);
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
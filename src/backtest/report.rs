@@ -0,0 +1,428 @@
+use std::{
+    fmt::Write as _,
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, Timelike, Utc};
+use rust_decimal::{prelude::ToPrimitive, Decimal};
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    backtest::{
+        engine::{BacktestConfig, BacktestResult, EquityPoint, Summary, Trade},
+        execution::ExecutionModel,
+        optimize::SweepResult,
+    },
+    consts::DEFAULT_REPORT_BINS,
+    error::SignalError,
+    invoke::gui::Setting,
+    middleware::file,
+};
+
+// グラフの描画サイズ (px)
+const CHART_WIDTH: f64 = 720.0;
+const CHART_HEIGHT: f64 = 200.0;
+// HTMLに載せるスイープ結果の上位件数
+const SWEEP_ROWS: usize = 20;
+
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct DrawdownPoint {
+    pub at: DateTime<Utc>,
+    // 直前の最高値からの下落幅 (正の値)
+    pub drawdown: Decimal,
+}
+
+/// 損益分布の1区間 [lower, upper)、最後の区間のみupperを含む
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Bucket {
+    pub lower: Decimal,
+    pub upper: Decimal,
+    pub count: usize,
+}
+
+/// 新規約定時刻 (UTC) の時間帯別成績
+#[derive(Debug, Clone, Copy, PartialEq, Default, Deserialize, Serialize)]
+pub struct HourStats {
+    pub hour: u32,
+    pub trades: usize,
+    pub wins: usize,
+    pub pnl: Decimal,
+}
+
+/// バックテスト結果のレポート
+/// JSONはそのまま機械処理に、HTMLは外部ファイルを参照しない単体のページとして出力する
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Report {
+    pub generated_at: DateTime<Utc>,
+    // 使用したパラメータ
+    pub setting: Setting,
+    pub execution: ExecutionModel,
+    pub ticks: usize,
+    pub summary: Summary,
+    pub equity: Vec<EquityPoint>,
+    pub drawdown: Vec<DrawdownPoint>,
+    pub distribution: Vec<Bucket>,
+    // 0-23時の24件
+    pub hourly: Vec<HourStats>,
+    // パラメータスイープの順位、単体のバックテストでは空
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub sweep: Vec<SweepResult>,
+}
+
+impl Report {
+    pub fn new(config: &BacktestConfig, result: &BacktestResult) -> Self {
+        Report {
+            generated_at: Utc::now(),
+            setting: config.setting.clone(),
+            execution: config.execution,
+            ticks: result.ticks,
+            summary: result.summary(),
+            equity: result.equity.clone(),
+            drawdown: drawdown_curve(&result.equity),
+            distribution: distribution(&result.trades, DEFAULT_REPORT_BINS),
+            hourly: hourly(&result.trades),
+            sweep: vec![],
+        }
+    }
+
+    // スイープ結果を添付する、settingは最良の結果に合わせておくこと
+    pub fn with_sweep(mut self, sweep: Vec<SweepResult>) -> Self {
+        self.sweep = sweep;
+        self
+    }
+
+    pub fn to_json(&self) -> Result<String, SignalError> {
+        serde_json::to_string_pretty(self).map_err(|e| SignalError::Parse(e.to_string()))
+    }
+
+    pub fn to_html(&self) -> String {
+        let mut html = String::new();
+        html.push_str("<!DOCTYPE html>\n<html lang=\"ja\">\n<head>\n<meta charset=\"utf-8\">\n");
+        html.push_str("<title>signalrs backtest report</title>\n");
+        html.push_str(STYLE);
+        html.push_str("</head>\n<body>\n");
+        let _ = writeln!(html, "<h1>Backtest report</h1>\n<p>generated at {}</p>", self.generated_at.to_rfc3339());
+
+        // 集計
+        let s = &self.summary;
+        html.push_str("<h2>Summary</h2>\n<table>\n");
+        for (label, value) in [
+            ("ticks", self.ticks.to_string()),
+            ("trades", s.trades.to_string()),
+            ("wins", s.wins.to_string()),
+            ("losses", s.losses.to_string()),
+            ("total pnl", s.total_pnl.to_string()),
+            ("win rate", s.win_rate.round_dp(4).to_string()),
+            ("max drawdown", s.max_drawdown.to_string()),
+        ] {
+            let _ = writeln!(html, "<tr><th>{}</th><td>{}</td></tr>", label, value);
+        }
+        html.push_str("</table>\n");
+
+        html.push_str("<h2>Equity</h2>\n");
+        let equity: Vec<f64> = self.equity.iter().map(|e| to_f64(e.equity)).collect();
+        html.push_str(&line_chart(&equity, "#2563eb"));
+
+        html.push_str("<h2>Drawdown</h2>\n");
+        let drawdown: Vec<f64> = self.drawdown.iter().map(|d| -to_f64(d.drawdown)).collect();
+        html.push_str(&line_chart(&drawdown, "#dc2626"));
+
+        html.push_str("<h2>Trade distribution</h2>\n");
+        let bars: Vec<(String, f64)> = self
+            .distribution
+            .iter()
+            .map(|b| (format!("{} - {}", b.lower.round_dp(6), b.upper.round_dp(6)), b.count as f64))
+            .collect();
+        html.push_str(&bar_chart(&bars));
+
+        html.push_str("<h2>Hourly performance (UTC)</h2>\n<table>\n<tr><th>hour</th><th>trades</th><th>wins</th><th>pnl</th></tr>\n");
+        for h in self.hourly.iter().filter(|h| h.trades > 0) {
+            let class = if h.pnl < Decimal::ZERO { " class=\"loss\"" } else { "" };
+            let _ = writeln!(
+                html,
+                "<tr><td>{:02}</td><td>{}</td><td>{}</td><td{}>{}</td></tr>",
+                h.hour, h.trades, h.wins, class, h.pnl
+            );
+        }
+        html.push_str("</table>\n");
+
+        html.push_str("<h2>Parameters</h2>\n");
+        let params = serde_json::json!({ "setting": self.setting, "execution": self.execution });
+        let _ = writeln!(
+            html,
+            "<pre>{}</pre>",
+            escape(&serde_json::to_string_pretty(&params).unwrap_or_default())
+        );
+
+        if !self.sweep.is_empty() {
+            let _ = writeln!(
+                html,
+                "<h2>Sweep (top {} / {})</h2>\n<table>\n<tr><th>#</th><th>speed</th><th>vol</th><th>interval</th><th>random</th><th>trades</th><th>pnl</th><th>win rate</th><th>max dd</th><th>score</th></tr>",
+                self.sweep.len().min(SWEEP_ROWS),
+                self.sweep.len()
+            );
+            for (i, r) in self.sweep.iter().take(SWEEP_ROWS).enumerate() {
+                let _ = writeln!(
+                    html,
+                    "<tr><td>{}</td><td>{:?}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td><td>{}</td></tr>",
+                    i + 1,
+                    r.params.speed,
+                    escape(&r.params.vol),
+                    r.params.interval,
+                    r.params.interval_random,
+                    r.summary.trades,
+                    r.summary.total_pnl,
+                    r.summary.win_rate.round_dp(4),
+                    r.summary.max_drawdown,
+                    r.score.round_dp(6)
+                );
+            }
+            html.push_str("</table>\n");
+        }
+
+        html.push_str("</body>\n</html>\n");
+        html
+    }
+
+    // CORE: {dir}/{name}.json と {dir}/{name}.html を書き出す
+    pub fn write(&self, dir: &Path, name: &str) -> Result<(PathBuf, PathBuf), SignalError> {
+        std::fs::create_dir_all(dir)?;
+        let json = dir.join(format!("{}.json", name));
+        let html = dir.join(format!("{}.html", name));
+        file::write(&json.to_string_lossy(), self)?;
+        std::fs::write(&html, self.to_html())?;
+        Ok((json, html))
+    }
+}
+
+// 確定損益の推移からドローダウンの推移を計算する
+pub fn drawdown_curve(equity: &[EquityPoint]) -> Vec<DrawdownPoint> {
+    let mut peak = Decimal::ZERO;
+    equity
+        .iter()
+        .map(|e| {
+            peak = peak.max(e.equity);
+            DrawdownPoint {
+                at: e.at,
+                drawdown: peak - e.equity,
+            }
+        })
+        .collect()
+}
+
+// 1取引あたりの損益をbins個の等幅区間に集計する
+pub fn distribution(trades: &[Trade], bins: usize) -> Vec<Bucket> {
+    let (Some(min), Some(max)) = (trades.iter().map(|t| t.pnl).min(), trades.iter().map(|t| t.pnl).max()) else {
+        return vec![];
+    };
+    // 全取引が同じ損益の場合は1区間とする
+    if bins == 0 || min == max {
+        return vec![Bucket {
+            lower: min,
+            upper: max,
+            count: trades.len(),
+        }];
+    }
+
+    let width = (max - min) / Decimal::from(bins);
+    let mut buckets: Vec<Bucket> = (0..bins)
+        .map(|i| Bucket {
+            lower: min + width * Decimal::from(i),
+            upper: if i + 1 == bins { max } else { min + width * Decimal::from(i + 1) },
+            count: 0,
+        })
+        .collect();
+    for t in trades {
+        let i = ((t.pnl - min) / width).floor().to_usize().unwrap_or(0).min(bins - 1);
+        buckets[i].count += 1;
+    }
+    buckets
+}
+
+pub fn hourly(trades: &[Trade]) -> Vec<HourStats> {
+    let mut hours: Vec<HourStats> = (0..24)
+        .map(|hour| HourStats {
+            hour,
+            ..Default::default()
+        })
+        .collect();
+    for t in trades {
+        let h = &mut hours[t.entried_at.hour() as usize];
+        h.trades += 1;
+        h.pnl += t.pnl;
+        if t.is_win() {
+            h.wins += 1;
+        }
+    }
+    hours
+}
+
+fn to_f64(d: Decimal) -> f64 {
+    d.to_f64().unwrap_or_default()
+}
+
+fn escape(s: &str) -> String {
+    s.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+// 値の列を等間隔の折れ線としてSVGで描画する、0の位置に基準線を引く
+fn line_chart(values: &[f64], color: &str) -> String {
+    if values.is_empty() {
+        return "<p>no data</p>\n".to_string();
+    }
+    let min = values.iter().cloned().fold(0.0, f64::min);
+    let max = values.iter().cloned().fold(0.0, f64::max);
+    let range = if max > min { max - min } else { 1.0 };
+    let step = if values.len() > 1 { CHART_WIDTH / (values.len() - 1) as f64 } else { 0.0 };
+    let y = |v: f64| CHART_HEIGHT - (v - min) / range * CHART_HEIGHT;
+
+    let points: Vec<String> = values
+        .iter()
+        .enumerate()
+        .map(|(i, v)| format!("{:.1},{:.1}", i as f64 * step, y(*v)))
+        .collect();
+    format!(
+        "<svg viewBox=\"0 0 {w} {h}\" width=\"{w}\" height=\"{h}\">\
+<line x1=\"0\" y1=\"{zero:.1}\" x2=\"{w}\" y2=\"{zero:.1}\" stroke=\"#9ca3af\" stroke-dasharray=\"4\"/>\
+<polyline fill=\"none\" stroke=\"{color}\" stroke-width=\"1.5\" points=\"{points}\"/></svg>\n<p>min {min} / max {max}</p>\n",
+        w = CHART_WIDTH,
+        h = CHART_HEIGHT,
+        zero = y(0.0),
+        color = color,
+        points = points.join(" "),
+        min = min,
+        max = max,
+    )
+}
+
+// ラベル付きの棒グラフ、ラベルはtitleとして表示する
+fn bar_chart(bars: &[(String, f64)]) -> String {
+    if bars.is_empty() {
+        return "<p>no data</p>\n".to_string();
+    }
+    let max = bars.iter().map(|(_, v)| *v).fold(0.0, f64::max).max(1.0);
+    let width = CHART_WIDTH / bars.len() as f64;
+
+    let mut svg = format!(
+        "<svg viewBox=\"0 0 {w} {h}\" width=\"{w}\" height=\"{h}\">",
+        w = CHART_WIDTH,
+        h = CHART_HEIGHT
+    );
+    for (i, (label, v)) in bars.iter().enumerate() {
+        let height = v / max * CHART_HEIGHT;
+        let _ = write!(
+            svg,
+            "<rect x=\"{:.1}\" y=\"{:.1}\" width=\"{:.1}\" height=\"{:.1}\" fill=\"#10b981\"><title>{}: {}</title></rect>",
+            i as f64 * width + 1.0,
+            CHART_HEIGHT - height,
+            (width - 2.0).max(1.0),
+            height,
+            escape(label),
+            v
+        );
+    }
+    svg.push_str("</svg>\n");
+    svg
+}
+
+const STYLE: &str = "<style>
+body { font-family: sans-serif; margin: 2em; color: #111827; }
+table { border-collapse: collapse; margin-bottom: 1em; }
+th, td { border: 1px solid #d1d5db; padding: 2px 8px; text-align: right; }
+th { background: #f3f4f6; }
+td.loss { color: #dc2626; }
+svg { background: #f9fafb; border: 1px solid #e5e7eb; }
+pre { background: #f3f4f6; padding: 1em; }
+</style>
+";
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::order_type::signal::Side;
+    use chrono::TimeZone;
+
+    fn trade(hour: u32, pnl: i64) -> Trade {
+        let at = Utc.with_ymd_and_hms(2024, 1, 1, hour, 0, 0).unwrap();
+        Trade {
+            symbol: "USDJPY".to_string(),
+            side: Side::Buy,
+            entry: Decimal::new(150_000, 3),
+            exit: Decimal::new(150_000 + pnl, 3),
+            size: Decimal::ONE,
+            entried_at: at,
+            exited_at: at + chrono::Duration::seconds(1),
+            pnl: Decimal::new(pnl, 3),
+        }
+    }
+
+    fn result() -> BacktestResult {
+        let trades = vec![trade(0, 10), trade(0, -30), trade(9, 20), trade(23, 5)];
+        let mut equity = vec![EquityPoint {
+            at: trades[0].entried_at,
+            equity: Decimal::ZERO,
+        }];
+        for t in trades.iter() {
+            let last = equity.last().unwrap().equity;
+            equity.push(EquityPoint {
+                at: t.exited_at,
+                equity: last + t.pnl,
+            });
+        }
+        BacktestResult {
+            trades,
+            equity,
+            ticks: 100,
+        }
+    }
+
+    #[test]
+    fn test_report_aggregates() {
+        let report = Report::new(&BacktestConfig::default(), &result());
+
+        // 0.010 → -0.020 で最高値から0.030下落
+        let drawdown: Vec<Decimal> = report.drawdown.iter().map(|d| d.drawdown).collect();
+        assert_eq!(drawdown.iter().max(), Some(&Decimal::new(30, 3)));
+        assert_eq!(report.summary.max_drawdown, Decimal::new(30, 3));
+
+        assert_eq!(report.hourly.len(), 24);
+        assert_eq!(report.hourly[0].trades, 2);
+        assert_eq!(report.hourly[0].pnl, Decimal::new(-20, 3));
+        assert_eq!(report.hourly[9].wins, 1);
+
+        // -0.030〜0.020を等分し、最大値は最後の区間に入る
+        let counts: usize = report.distribution.iter().map(|b| b.count).sum();
+        assert_eq!(counts, 4);
+        assert_eq!(report.distribution.first().unwrap().count, 1);
+        assert_eq!(report.distribution.last().unwrap().count, 1);
+        assert_eq!(report.distribution.last().unwrap().upper, Decimal::new(20, 3));
+    }
+
+    #[test]
+    fn test_distribution_edge_cases() {
+        assert!(distribution(&[], 10).is_empty());
+        let same = distribution(&[trade(0, 5), trade(1, 5)], 10);
+        assert_eq!(same.len(), 1);
+        assert_eq!(same[0].count, 2);
+    }
+
+    #[test]
+    fn test_report_write() {
+        let dir = std::env::temp_dir().join(format!("signalrs_report_{}", std::process::id()));
+        let report = Report::new(&BacktestConfig::default(), &result());
+        let (json, html) = report.write(&dir, "backtest").unwrap();
+
+        let loaded: Report = file::read(&json.to_string_lossy()).unwrap();
+        assert_eq!(loaded.summary, report.summary);
+        assert!(loaded.sweep.is_empty());
+
+        let html = std::fs::read_to_string(html).unwrap();
+        assert!(html.contains("<polyline"));
+        assert!(html.contains("Hourly performance"));
+        assert!(!html.contains("<script"));
+        std::fs::remove_dir_all(&dir).unwrap();
+    }
+}
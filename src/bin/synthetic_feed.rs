@@ -0,0 +1,117 @@
+//! MT5のEAの代わりにtickを送信するローカル検証用のフィード
+//!
+//! cargo run --bin synthetic_feed -- --addr 127.0.0.1:8080 --model gbm --rate 20 --flags 1,2 --flag-probability 0.01
+
+use std::{process::ExitCode, str::FromStr};
+
+use chrono::Utc;
+use log::{error, info};
+use rust_decimal::Decimal;
+use signalrs::middleware::synthetic::{self, PriceModel, SyntheticConfig, SyntheticFeed};
+
+const USAGE: &str = "usage: synthetic_feed [options]
+  --addr <host:port>          送信先/待受アドレス (default: 127.0.0.1:8080)
+  --mode <connect|serve>      connect: TcpClient::received_server へ接続, serve: TcpClient::connect を待ち受け (default: connect)
+  --model <walk|gbm|regime|jump>  価格モデル (default: walk)
+  --volatility <f64>          1tickあたりの変動幅、walkは価格差・その他は対数収益率 (default: walk 0.002, その他 0.00002)
+  --symbol <str>              (default: USDJPY)
+  --price <f64>               初期のbid (default: 150)
+  --spread <decimal>          ask - bid (default: 0.002)
+  --digits <u32>              価格の小数桁数 (default: 3)
+  --rate <f64>                1秒あたりのtick数 (default: 10)
+  --flags <u8,u8,...>         付与するflagの候補
+  --flag-probability <f64>    各tickにflagを付与する確率 (default: 0)
+  --count <usize>             送信件数、省略時は無制限
+  --seed <u64>                (default: 0)";
+
+struct Args {
+    addr: String,
+    serve: bool,
+    count: Option<usize>,
+    config: SyntheticConfig,
+}
+
+fn value<T: FromStr>(name: &str, v: Option<String>) -> Result<T, String> {
+    let v = v.ok_or_else(|| format!("missing value for {}", name))?;
+    v.parse().map_err(|_| format!("invalid value for {}: {}", name, v))
+}
+
+fn parse(mut args: impl Iterator<Item = String>) -> Result<Args, String> {
+    let mut addr = "127.0.0.1:8080".to_string();
+    let mut serve = false;
+    let mut count = None;
+    let mut model = "walk".to_string();
+    let mut volatility = None;
+    let mut config = SyntheticConfig::default();
+
+    while let Some(arg) = args.next() {
+        match arg.as_str() {
+            "--addr" => addr = value(&arg, args.next())?,
+            "--mode" => {
+                serve = match value::<String>(&arg, args.next())?.as_str() {
+                    "connect" => false,
+                    "serve" => true,
+                    m => return Err(format!("unknown mode: {}", m)),
+                }
+            }
+            "--model" => model = value(&arg, args.next())?,
+            "--volatility" => volatility = Some(value::<f64>(&arg, args.next())?),
+            "--symbol" => config.symbol = value(&arg, args.next())?,
+            "--price" => config.price = value(&arg, args.next())?,
+            "--spread" => config.spread = value::<Decimal>(&arg, args.next())?,
+            "--digits" => config.digits = value(&arg, args.next())?,
+            "--rate" => config.rate = value(&arg, args.next())?,
+            "--flags" => {
+                config.flags = value::<String>(&arg, args.next())?
+                    .split(',')
+                    .map(|f| f.trim().parse::<u8>().map_err(|_| format!("invalid flag: {}", f)))
+                    .collect::<Result<_, _>>()?
+            }
+            "--flag-probability" => config.flag_probability = value(&arg, args.next())?,
+            "--count" => count = Some(value(&arg, args.next())?),
+            "--seed" => config.seed = value(&arg, args.next())?,
+            "-h" | "--help" => return Err(USAGE.to_string()),
+            _ => return Err(format!("unknown option: {}\n{}", arg, USAGE)),
+        }
+    }
+
+    let default = if model == "walk" || model == "random-walk" { 0.002 } else { 0.000_02 };
+    config.model =
+        PriceModel::from_name(&model, volatility.unwrap_or(default)).ok_or_else(|| format!("unknown model: {}", model))?;
+
+    Ok(Args {
+        addr,
+        serve,
+        count,
+        config,
+    })
+}
+
+fn main() -> ExitCode {
+    signalrs::middleware::utils::set_env_for_logger("info");
+
+    let args = match parse(std::env::args().skip(1)) {
+        Ok(args) => args,
+        Err(e) => {
+            eprintln!("{}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    let feed = SyntheticFeed::new(args.config, Utc::now());
+    let result = if args.serve {
+        synthetic::serve(&args.addr, feed, args.count)
+    } else {
+        synthetic::connect(&args.addr, feed, args.count)
+    };
+    match result {
+        Ok(sent) => {
+            info!("sent {} ticks", sent);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            error!("{}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
@@ -1,3 +1,10 @@
 pub const DEFAULT_TICKER_BUFFER_SIZE: usize = 144;
+pub const MAX_TICKER_BUFFER_SIZE: usize = 100_000;
+pub const DEFAULT_DUPLICATE_LOOKBACK: usize = 16;
+pub const DEFAULT_LATENCY_WINDOW: usize = 1_000;
+pub const DEFAULT_SKEW_TOLERANCE_MICROS: i64 = 50_000;
+pub const DEFAULT_REPORT_BINS: usize = 20;
+pub const DEFAULT_MONTE_CARLO_RUNS: usize = 1_000;
+pub const DEFAULT_EXIT_POLL_MS: u64 = 10;
 pub const DEFAULT_ORDER_HISTORY_LIMIT: usize = 8;
 pub const DEFAULT_SAVE_PATH: &str = "./.save/setting.json";
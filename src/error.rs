@@ -14,6 +14,27 @@ pub enum SignalError {
     LockPoisoned(String),
     #[error("Channel send failed")]
     ChannelClosed,
+    #[error("Insufficient history: required {required_micros} micros, covered {covered_micros} micros")]
+    InsufficientHistory {
+        required_micros: i64,
+        covered_micros: i64,
+    },
+    #[error("Spread too wide: {spread} > {limit}")]
+    SpreadTooWide {
+        spread: rust_decimal::Decimal,
+        limit: rust_decimal::Decimal,
+    },
+    #[error("Stale tick: latency {latency_micros} micros exceeds {limit_micros} micros")]
+    StaleTick {
+        latency_micros: i64,
+        limit_micros: i64,
+    },
+    #[error("Invalid position transition: {action} while {state}")]
+    InvalidTransition { state: String, action: String },
+    #[error("Invalid command: {0}")]
+    InvalidCommand(String),
+    #[error("Indicator error: {0}")]
+    Indicator(String),
     #[error("Parse error: {0}")]
     Parse(String),
     #[error("IO error: {0}")]
@@ -15,7 +15,15 @@ use serde_json::{json, Value};
 
 use tauri::State;
 
-use crate::middleware::{mouse, utils};
+use crate::{
+    middleware::{latency::LatencySnapshot, mouse, sanitizer::RejectCounts, ticker::SpreadGate, utils},
+    order_type::{
+        breakout::BreakoutParams,
+        position::PositionState,
+        stops::{ExitRule, Watch},
+        zscore::ZScoreParams,
+    },
+};
 
 #[derive(Debug, Clone, Default)]
 pub struct WrappedData {
@@ -101,6 +109,14 @@ impl Data {
                     value: "3".to_string(),
                     label: "決済注文のみ".to_string(),
                 },
+                Op {
+                    value: "4".to_string(),
+                    label: "Zスコア".to_string(),
+                },
+                Op {
+                    value: "5".to_string(),
+                    label: "ブレイクアウト".to_string(),
+                },
                 Op {
                     value: "99".to_string(),
                     label: "独自フラグ".to_string(),
@@ -157,6 +173,25 @@ pub struct Status {
     pub ltp: Decimal,
     pub orders: VecDeque<Order>,
     pub updated_at: DateTime<Utc>,
+
+    // 異常tickの除外件数
+    #[serde(default)]
+    pub rejected: RejectCounts,
+    // 受信遅延の集計
+    #[serde(default)]
+    pub latency: LatencySnapshot,
+    // 決済待ちの建玉
+    #[serde(default)]
+    pub watch: Option<Watch>,
+    // 建玉の状態
+    #[serde(default)]
+    pub position: PositionState,
+    // 建玉の数量 (新規のクリック回数)
+    #[serde(default)]
+    pub volume: u32,
+    // 最後に実行したOriginの注文指示のid
+    #[serde(default)]
+    pub signal_id: Option<String>,
 }
 
 impl Default for Status {
@@ -170,6 +205,13 @@ impl Default for Status {
             ltp: Decimal::ZERO,
             orders: VecDeque::new(),
             updated_at: Utc::now(),
+
+            rejected: RejectCounts::default(),
+            latency: LatencySnapshot::default(),
+            watch: None,
+            position: PositionState::Flat,
+            volume: 0,
+            signal_id: None,
         }
     }
 }
@@ -185,6 +227,13 @@ impl Status {
             ltp: Decimal::ZERO,
             orders: VecDeque::new(),
             updated_at: Utc::now(),
+
+            rejected: RejectCounts::default(),
+            latency: LatencySnapshot::default(),
+            watch: None,
+            position: PositionState::Flat,
+            volume: 0,
+            signal_id: None,
         }
     }
 
@@ -211,6 +260,14 @@ impl Status {
         self.orders.push_back(order);
     }
 
+    pub fn update_rejected(&mut self, counts: RejectCounts) {
+        self.rejected = counts;
+    }
+
+    pub fn update_latency(&mut self, latency: LatencySnapshot) {
+        self.latency = latency;
+    }
+
     // 指定配列数に縮小する
     pub fn shrink(&mut self, limit_length: usize) {
         // limit_length以上の古い部分を捨てる
@@ -305,16 +362,69 @@ pub enum OrderType {
     SellEntry,
     #[serde(rename = "3")]
     ExitOnly,
+    #[serde(rename = "4")]
+    ZScore,
+    #[serde(rename = "5")]
+    Breakout,
     #[serde(rename = "99")]
     Custom,
 }
 
+impl OrderType {
+    // StrategyRegistryの組み込み名
+    pub fn strategy_name(&self) -> &'static str {
+        match self {
+            OrderType::Simple => "simple",
+            OrderType::BuyEntry => "buy_entry",
+            OrderType::SellEntry => "sell_entry",
+            OrderType::ExitOnly => "exit_only",
+            OrderType::ZScore => "zscore",
+            OrderType::Breakout => "breakout",
+            OrderType::Custom => "custom",
+        }
+    }
+}
+
 impl Default for OrderType {
     fn default() -> Self {
         OrderType::Simple
     }
 }
 
+/// Zスコアの売買方向
+/// Reversion: 平均への回帰を狙い逆張り、Momentum: 乖離の方向へ順張り
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum ZScoreMode {
+    #[default]
+    #[serde(rename = "0")]
+    Reversion,
+    #[serde(rename = "1")]
+    Momentum,
+}
+
+/// 決済待ちの建玉と逆方向のシグナルの扱い
+/// Ignore: 無視して予定通り決済、Cancel: 予定の決済を取り消して即時決済、Replace: 即時決済後に逆方向へ新規
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum OppositeSignal {
+    #[default]
+    #[serde(rename = "0")]
+    Ignore,
+    #[serde(rename = "1")]
+    Cancel,
+    #[serde(rename = "2")]
+    Replace,
+}
+
+/// ブレイクアウトの高値・安値を求める範囲の単位
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum BreakoutUnit {
+    #[default]
+    #[serde(rename = "0")]
+    Seconds,
+    #[serde(rename = "1")]
+    Bars,
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
 pub enum Speed {
     #[serde(rename = "0")]
@@ -352,6 +462,89 @@ pub struct Setting {
     pub vol: String,
     pub interval: u32,
     pub interval_random: bool,
+    // スプレッドの上限、空文字で無効
+    #[serde(default)]
+    pub spread_max: String,
+    // 直近平均スプレッドに対する上限倍率、空文字で無効
+    #[serde(default)]
+    pub spread_ratio: String,
+    // 受信遅延の上限 (ms)、超えたtickでは注文判定を行わない、0で無効
+    #[serde(default)]
+    pub stale_ms: u32,
+    // StrategyRegistryに登録した注文ロジック名、空文字でorder_typeの組み込みを使用
+    #[serde(default)]
+    pub strategy: String,
+    // order_type Zスコア: 対象の価格 (bid/ask/mid)
+    #[serde(default = "default_zscore_field")]
+    pub zscore_field: String,
+    // エントリーするZスコアの絶対値
+    #[serde(default = "default_zscore_entry")]
+    pub zscore_entry: String,
+    // 決済するZスコアの絶対値、外側から内側へ戻った時点で決済する
+    #[serde(default = "default_zscore_exit")]
+    pub zscore_exit: String,
+    #[serde(default)]
+    pub zscore_mode: ZScoreMode,
+    // 1pipの価格幅、pips指定の設定値に使用する
+    #[serde(default = "default_pip_size")]
+    pub pip_size: String,
+    // order_type ブレイクアウト: 直近N秒またはN本の足の高値・安値
+    #[serde(default = "default_breakout_window")]
+    pub breakout_window: u32,
+    #[serde(default)]
+    pub breakout_unit: BreakoutUnit,
+    // 足で判定する場合の足の長さ (秒)
+    #[serde(default = "default_breakout_bar_secs")]
+    pub breakout_bar_secs: u32,
+    // 高値・安値に上乗せする幅 (pips)
+    #[serde(default = "default_breakout_buffer")]
+    pub breakout_buffer: String,
+    // ブレイク後に外側で続けて受信するtick数、0で即時
+    #[serde(default)]
+    pub breakout_confirm: u32,
+    // 建値からの利確幅 (pips)、空文字で無効
+    #[serde(default)]
+    pub take_profit: String,
+    // 建値からの損切り幅 (pips)、空文字で無効
+    #[serde(default)]
+    pub stop_loss: String,
+    // 最も有利な価格からの戻り幅 (pips)、空文字で無効
+    #[serde(default)]
+    pub trailing_stop: String,
+    // 逆指値を建値へ移動する利益幅 (pips)、空文字で無効
+    #[serde(default)]
+    pub break_even: String,
+    // 決済待ちの建玉と逆方向のシグナル
+    #[serde(default)]
+    pub opposite: OppositeSignal,
+}
+
+fn default_pip_size() -> String {
+    "0.01".to_string()
+}
+
+fn default_breakout_window() -> u32 {
+    60
+}
+
+fn default_breakout_bar_secs() -> u32 {
+    60
+}
+
+fn default_breakout_buffer() -> String {
+    "0".to_string()
+}
+
+fn default_zscore_field() -> String {
+    "bid".to_string()
+}
+
+fn default_zscore_entry() -> String {
+    "2".to_string()
+}
+
+fn default_zscore_exit() -> String {
+    "0.5".to_string()
 }
 
 impl Default for Setting {
@@ -369,6 +562,25 @@ impl Setting {
             vol: "0.1".to_string(),
             interval: 10,
             interval_random: false,
+            spread_max: "".to_string(),
+            spread_ratio: "".to_string(),
+            stale_ms: 0,
+            strategy: "".to_string(),
+            zscore_field: default_zscore_field(),
+            zscore_entry: default_zscore_entry(),
+            zscore_exit: default_zscore_exit(),
+            zscore_mode: ZScoreMode::Reversion,
+            pip_size: default_pip_size(),
+            breakout_window: default_breakout_window(),
+            breakout_unit: BreakoutUnit::Seconds,
+            breakout_bar_secs: default_breakout_bar_secs(),
+            breakout_buffer: default_breakout_buffer(),
+            breakout_confirm: 0,
+            take_profit: "".to_string(),
+            stop_loss: "".to_string(),
+            trailing_stop: "".to_string(),
+            break_even: "".to_string(),
+            opposite: OppositeSignal::Ignore,
         }
     }
     // CORE: 設定値を条件用数値に変換する
@@ -386,17 +598,78 @@ impl Setting {
 
     // 設定値から待機時間を取得する
     pub fn get_sleep_ms(&self) -> u64 {
-        if self.interval_random {
+        self.sleep_ms_with(&mut rand::thread_rng())
+    }
+
+    // 乱数生成器を指定して待機時間を取得する、バックテストで再現性を保つために使用する
+    pub fn sleep_ms_with<R: Rng>(&self, rng: &mut R) -> u64 {
+        if self.interval_random && self.interval > 0 {
             // interval sec to min, max millisec
             let min_ms = (self.interval as f32 * 0.5 * 1000f32) as u32;
             let max_ms = (self.interval as f32 * 1.5 * 1000f32) as u32;
-            let mut rng = rand::thread_rng();
             rng.gen_range(min_ms..max_ms) as u64
         } else {
             self.interval as u64 * 1000u64
         }
     }
 
+    // スプレッドによるエントリー制限を取得する
+    // 空文字・不正値・0以下は無効とする
+    pub fn spread_gate(&self) -> SpreadGate {
+        let parse = |s: &str| Decimal::from_str(s.trim()).ok().filter(|v| *v > Decimal::ZERO);
+        SpreadGate {
+            max: parse(&self.spread_max),
+            ratio: parse(&self.spread_ratio),
+        }
+    }
+
+    // 選択中の注文方式の判定条件を検証する、保存時に1度だけ行う
+    pub fn ok(&self) -> Result<(), String> {
+        match self.order_type {
+            OrderType::ZScore if ZScoreParams::from_setting(self).is_none() => Err("error: invalid zscore setting".to_string()),
+            OrderType::Breakout if BreakoutParams::from_setting(self).is_none() => Err("error: invalid breakout setting".to_string()),
+            _ => Ok(()),
+        }
+    }
+
+    // 価格による決済条件を取得する、pipsを価格差へ換算する
+    // 空文字・不正値・0以下は無効とする
+    pub fn exit_rule(&self) -> ExitRule {
+        let parse = |s: &str| {
+            Decimal::from_str(s.trim())
+                .ok()
+                .filter(|v| *v > Decimal::ZERO)
+                .map(|v| v * self.pip())
+        };
+        ExitRule {
+            take_profit: parse(&self.take_profit),
+            stop_loss: parse(&self.stop_loss),
+            trailing_stop: parse(&self.trailing_stop),
+            break_even: parse(&self.break_even),
+        }
+    }
+
+    // 1pipの価格幅、不正値・0以下は0.01とする
+    pub fn pip(&self) -> Decimal {
+        Decimal::from_str(self.pip_size.trim())
+            .ok()
+            .filter(|v| *v > Decimal::ZERO)
+            .unwrap_or(Decimal::new(1, 2))
+    }
+
+    // 使用する注文ロジック名
+    pub fn strategy_name(&self) -> &str {
+        match self.strategy.trim() {
+            "" => self.order_type.strategy_name(),
+            name => name,
+        }
+    }
+
+    // 受信遅延の上限 (micros)
+    pub fn stale_micros(&self) -> Option<i64> {
+        (self.stale_ms > 0).then_some(self.stale_ms as i64 * 1000)
+    }
+
     /// 文字列フィールドをパース済みの型付き構造体として返す
     pub fn parsed(&self) -> ParsedSetting {
         ParsedSetting {
@@ -404,6 +677,7 @@ impl Setting {
             vol: Decimal::from_str(self.vol.as_str()).unwrap_or(Decimal::new(1, 1)),
             interval: self.interval,
             interval_random: self.interval_random,
+            spread_gate: self.spread_gate(),
         }
     }
 }
@@ -414,6 +688,7 @@ pub struct ParsedSetting {
     pub vol: Decimal,
     pub interval: u32,
     pub interval_random: bool,
+    pub spread_gate: SpreadGate,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -463,6 +738,18 @@ pub async fn run(state: State<'_, Arc<RwLock<Data>>>, t: u8) -> Result<String, S
             };
             json!(status).to_string()
         }
+        3 => {
+            // reset position, ブラウザ側で手動決済した場合などに使用する
+            {
+                let mut locked_data = state.write().unwrap();
+                locked_data.status.position = PositionState::Flat;
+                locked_data.status.watch = None;
+                locked_data.status.volume = 0;
+                locked_data.status.is_processing = false;
+                locked_data.status.message = "position reset".to_string();
+            }
+            "position reset".to_string()
+        }
         _ => Err("error: undefined".to_string())?,
     };
 
@@ -491,6 +778,7 @@ pub async fn set(state: State<'_, Arc<RwLock<Data>>>, t: u8, v: Value) -> Result
         1 => {
             // 設定を受け取る
             let recived: Setting = serde_json::from_value(v).unwrap();
+            recived.ok()?;
             let recived = {
                 let mut locked_data = state.write().unwrap();
 
@@ -654,6 +942,10 @@ mod test {
         assert_eq!(json, "\"1\"");
         let json = serde_json::to_string(&OrderType::Custom).unwrap();
         assert_eq!(json, "\"99\"");
+        let ot: OrderType = serde_json::from_str("\"4\"").unwrap();
+        assert_eq!(ot, OrderType::ZScore);
+        let ot: OrderType = serde_json::from_str("\"5\"").unwrap();
+        assert_eq!(ot, OrderType::Breakout);
 
         // Deserialize
         let ot: OrderType = serde_json::from_str("\"0\"").unwrap();
@@ -730,6 +1022,59 @@ mod test {
         assert_eq!(p.vol, Decimal::from_str("0.1").unwrap());
         assert_eq!(p.interval, 10);
         assert!(!p.interval_random);
+        assert_eq!(p.spread_gate, SpreadGate::default());
+    }
+
+    #[test]
+    fn test_setting_exit_rule() {
+        let mut s = Setting::new();
+        assert_eq!(s.exit_rule(), ExitRule::default());
+
+        s.take_profit = "5".to_string();
+        s.stop_loss = "-1".to_string();
+        let rule = s.exit_rule();
+        assert_eq!(rule.take_profit, Some(Decimal::from_str("0.05").unwrap()));
+        assert_eq!(rule.stop_loss, None);
+
+        s.trailing_stop = "3".to_string();
+        s.break_even = "2".to_string();
+        let rule = s.exit_rule();
+        assert_eq!(rule.trailing_stop, Some(Decimal::from_str("0.03").unwrap()));
+        assert_eq!(rule.break_even, Some(Decimal::from_str("0.02").unwrap()));
+
+        s.pip_size = "0.0001".to_string();
+        assert_eq!(s.exit_rule().take_profit, Some(Decimal::from_str("0.0005").unwrap()));
+    }
+
+    #[test]
+    fn test_setting_ok() {
+        let mut s = Setting::new();
+        s.breakout_window = 0;
+        assert!(s.ok().is_ok());
+
+        // 選択中の注文方式のみ検証する
+        s.order_type = OrderType::Breakout;
+        assert!(s.ok().is_err());
+        s.breakout_window = 1;
+        s.breakout_bar_secs = 1;
+        assert!(s.ok().is_ok());
+        s.breakout_buffer = "-1".to_string();
+        assert!(s.ok().is_err());
+    }
+
+    #[test]
+    fn test_setting_spread_gate() {
+        let mut s = Setting::new();
+        s.spread_max = "0.05".to_string();
+        s.spread_ratio = "abc".to_string();
+        let gate = s.spread_gate();
+        assert_eq!(gate.max, Some(Decimal::from_str("0.05").unwrap()));
+        assert_eq!(gate.ratio, None);
+
+        // 旧形式の設定ファイルは無効として読み込む
+        let json = r#"{"tcp":"8080","order_type":"0","speed":"1","vol":"0.1","interval":10,"interval_random":false}"#;
+        let s: Setting = serde_json::from_str(json).unwrap();
+        assert_eq!(s.spread_gate(), SpreadGate::default());
     }
 
     #[test]
@@ -780,6 +1125,25 @@ mod test {
         assert_eq!(status.orders[0].entry, Decimal::new(4, 0));
     }
 
+    #[test]
+    fn test_status_update_rejected() {
+        let mut status = Status::new();
+        assert_eq!(status.rejected.total(), 0);
+        status.update_rejected(RejectCounts { crossed: 2, jump: 1, ..Default::default() });
+        assert_eq!(status.rejected.total(), 3);
+
+        let json = serde_json::to_value(&status).unwrap();
+        assert_eq!(json["rejected"]["crossed"], 2);
+    }
+
+    #[test]
+    fn test_setting_stale_micros() {
+        let mut s = Setting::new();
+        assert_eq!(s.stale_micros(), None);
+        s.stale_ms = 250;
+        assert_eq!(s.stale_micros(), Some(250_000));
+    }
+
     // --- Order ---
     #[test]
     fn test_order_new() {
@@ -826,7 +1190,7 @@ mod test {
     #[test]
     fn test_data_default() {
         let d = Data::default();
-        assert_eq!(d.order_type.len(), 5);
+        assert_eq!(d.order_type.len(), 7);
         assert_eq!(d.speed.len(), 4);
         assert_eq!(d.host, "localhost");
     }
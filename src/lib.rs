@@ -1,3 +1,10 @@
+pub mod backtest {
+    pub mod engine;
+    pub mod execution;
+    pub mod monte_carlo;
+    pub mod optimize;
+    pub mod report;
+}
 pub mod consts;
 pub mod error;
 
@@ -6,18 +13,33 @@ pub mod invoke {
 }
 
 pub mod middleware {
+    pub mod command;
     pub mod file;
+    pub mod indicator;
+    pub mod latency;
     pub mod mouse;
+    pub mod recorder;
+    pub mod replay;
+    pub mod rolling;
+    pub mod sanitizer;
+    pub mod synthetic;
     pub mod tcp;
     pub mod ticker;
     pub mod utils;
 }
 
 pub mod order_type {
+    pub mod breakout;
     pub mod choose;
     pub mod entry;
     pub mod exit;
     pub mod origin;
+    pub mod position;
     pub mod process;
+    pub mod scheduler;
+    pub mod signal;
     pub mod simple;
+    pub mod stops;
+    pub mod strategy;
+    pub mod zscore;
 }
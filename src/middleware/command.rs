@@ -0,0 +1,119 @@
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::middleware::ticker::Ticker;
+
+/// 売買方向
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Side {
+    Buy,
+    Sell,
+}
+
+impl Side {
+    // 価格差の符号から方向を決める、0は方向なし
+    pub fn from_diff(diff: Decimal) -> Option<Self> {
+        if diff > Decimal::ZERO {
+            Some(Side::Buy)
+        } else if diff < Decimal::ZERO {
+            Some(Side::Sell)
+        } else {
+            None
+        }
+    }
+
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            Side::Buy => "buy",
+            Side::Sell => "sell",
+        }
+    }
+
+    pub fn opposite(&self) -> Self {
+        match self {
+            Side::Buy => Side::Sell,
+            Side::Sell => Side::Buy,
+        }
+    }
+
+    // 損益計算用の符号
+    pub fn sign(&self) -> Decimal {
+        match self {
+            Side::Buy => Decimal::ONE,
+            Side::Sell => Decimal::NEGATIVE_ONE,
+        }
+    }
+
+    // 新規約定価格: 買いはask、売りはbid
+    pub fn entry_price(&self, t: &Ticker) -> Decimal {
+        match self {
+            Side::Buy => t.ask,
+            Side::Sell => t.bid,
+        }
+    }
+
+    // 決済約定価格: 買いはbid、売りはask
+    pub fn exit_price(&self, t: &Ticker) -> Decimal {
+        match self {
+            Side::Buy => t.bid,
+            Side::Sell => t.ask,
+        }
+    }
+}
+
+/// commandの注文動作
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+#[serde(rename_all = "kebab-case")]
+pub enum Action {
+    // 新規注文、hold_ms指定時は保有時間を経て決済する
+    Entry,
+    // 決済注文、side指定時は同じ方向の建玉のみ
+    Exit,
+    // 建玉を決済し、逆方向へ新規注文
+    Reverse,
+    // 方向を問わず全数量を決済
+    CloseAll,
+    // volume分のみ決済
+    PartialClose,
+}
+
+/// tick JSONのcommandで受け取る注文指示
+/// flagの整数では表せない数量・保有時間・idを指定する
+#[derive(Debug, Clone, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Command {
+    pub action: Action,
+    #[serde(default)]
+    pub side: Option<Side>,
+    // 数量、新規・決済ボタンのクリック回数
+    #[serde(default)]
+    pub volume: Option<u32>,
+    // 新規後に決済するまでの最大保有時間
+    #[serde(default)]
+    pub hold_ms: Option<u64>,
+    // EAが付与するシグナルのid、同じidは一度だけ実行する
+    #[serde(default)]
+    pub id: Option<String>,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_side_prices() {
+        let t = Ticker {
+            bid: Decimal::new(150_000, 3),
+            ask: Decimal::new(150_002, 3),
+            ..Default::default()
+        };
+        assert_eq!(Side::Buy.entry_price(&t), t.ask);
+        assert_eq!(Side::Buy.exit_price(&t), t.bid);
+        assert_eq!(Side::Sell.entry_price(&t), t.bid);
+        assert_eq!(Side::Sell.exit_price(&t), t.ask);
+        assert_eq!(Side::from_diff(Decimal::ONE), Some(Side::Buy));
+        assert_eq!(Side::from_diff(Decimal::ZERO), None);
+        assert_eq!(Side::Sell.opposite(), Side::Buy);
+        assert_eq!(serde_json::to_string(&Side::Sell).unwrap(), r#""sell""#);
+    }
+}
@@ -0,0 +1,352 @@
+use rust_decimal::prelude::ToPrimitive;
+use serde::{Deserialize, Serialize};
+use ta::{
+    indicators::{
+        AverageTrueRange, BollingerBands, ExponentialMovingAverage,
+        MovingAverageConvergenceDivergence, RelativeStrengthIndex, SimpleMovingAverage,
+    },
+    DataItem, Next,
+};
+
+use crate::{
+    error::SignalError,
+    middleware::ticker::{Bar, BarInterval, Ticker},
+};
+
+/// 指標の種類と期間
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum IndicatorKind {
+    Sma(usize),
+    Ema(usize),
+    Rsi(usize),
+    // 期間, 標準偏差の倍率
+    Bollinger(usize, f64),
+    Atr(usize),
+    // 短期, 長期, シグナル
+    Macd(usize, usize, usize),
+}
+
+impl IndicatorKind {
+    // 値が安定するまでに必要な入力数
+    fn warmup(&self) -> usize {
+        match *self {
+            IndicatorKind::Sma(n)
+            | IndicatorKind::Ema(n)
+            | IndicatorKind::Rsi(n)
+            | IndicatorKind::Bollinger(n, _)
+            | IndicatorKind::Atr(n) => n,
+            IndicatorKind::Macd(_, slow, signal) => slow + signal,
+        }
+    }
+}
+
+/// 指標の更新元
+/// Tick: 受信tick毎のmid値、Bar: 指定足の確定毎
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum IndicatorSource {
+    Tick,
+    Bar(BarInterval),
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct IndicatorConfig {
+    pub name: String,
+    pub kind: IndicatorKind,
+    pub source: IndicatorSource,
+}
+
+impl IndicatorConfig {
+    pub fn new(name: &str, kind: IndicatorKind, source: IndicatorSource) -> Self {
+        IndicatorConfig {
+            name: name.to_string(),
+            kind,
+            source,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Serialize)]
+pub enum IndicatorValue {
+    Single(f64),
+    Bands { upper: f64, average: f64, lower: f64 },
+    Macd { macd: f64, signal: f64, histogram: f64 },
+}
+
+impl IndicatorValue {
+    // 代表値: Bandsは中心線、Macdはmacd線
+    pub fn value(&self) -> f64 {
+        match *self {
+            IndicatorValue::Single(v) => v,
+            IndicatorValue::Bands { average, .. } => average,
+            IndicatorValue::Macd { macd, .. } => macd,
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+enum Engine {
+    Sma(SimpleMovingAverage),
+    Ema(ExponentialMovingAverage),
+    Rsi(RelativeStrengthIndex),
+    Bollinger(BollingerBands),
+    Atr(AverageTrueRange),
+    Macd(MovingAverageConvergenceDivergence),
+}
+
+impl Engine {
+    fn new(kind: IndicatorKind) -> ta::errors::Result<Self> {
+        Ok(match kind {
+            IndicatorKind::Sma(n) => Engine::Sma(SimpleMovingAverage::new(n)?),
+            IndicatorKind::Ema(n) => Engine::Ema(ExponentialMovingAverage::new(n)?),
+            IndicatorKind::Rsi(n) => Engine::Rsi(RelativeStrengthIndex::new(n)?),
+            IndicatorKind::Bollinger(n, k) => Engine::Bollinger(BollingerBands::new(n, k)?),
+            IndicatorKind::Atr(n) => Engine::Atr(AverageTrueRange::new(n)?),
+            IndicatorKind::Macd(fast, slow, signal) => {
+                Engine::Macd(MovingAverageConvergenceDivergence::new(fast, slow, signal)?)
+            }
+        })
+    }
+
+    fn next_price(&mut self, v: f64) -> IndicatorValue {
+        match self {
+            Engine::Sma(i) => IndicatorValue::Single(i.next(v)),
+            Engine::Ema(i) => IndicatorValue::Single(i.next(v)),
+            Engine::Rsi(i) => IndicatorValue::Single(i.next(v)),
+            Engine::Atr(i) => IndicatorValue::Single(i.next(v)),
+            Engine::Bollinger(i) => {
+                let o = i.next(v);
+                IndicatorValue::Bands {
+                    upper: o.upper,
+                    average: o.average,
+                    lower: o.lower,
+                }
+            }
+            Engine::Macd(i) => {
+                let o = i.next(v);
+                IndicatorValue::Macd {
+                    macd: o.macd,
+                    signal: o.signal,
+                    histogram: o.histogram,
+                }
+            }
+        }
+    }
+
+    fn next_item(&mut self, item: &DataItem) -> IndicatorValue {
+        match self {
+            // ATRのみ高値・安値を使用する
+            Engine::Atr(i) => IndicatorValue::Single(i.next(item)),
+            _ => self.next_price(ta::Close::close(item)),
+        }
+    }
+}
+
+#[derive(Debug, Clone)]
+struct Indicator {
+    config: IndicatorConfig,
+    engine: Engine,
+    count: usize,
+    value: Option<IndicatorValue>,
+}
+
+impl Indicator {
+    fn new(config: IndicatorConfig) -> Result<Self, SignalError> {
+        let engine = Engine::new(config.kind)
+            .map_err(|e| SignalError::Indicator(format!("{}: {}", config.name, e)))?;
+        Ok(Indicator {
+            config,
+            engine,
+            count: 0,
+            value: None,
+        })
+    }
+
+    fn update(&mut self, value: IndicatorValue) {
+        self.count += 1;
+        self.value = Some(value);
+    }
+
+    fn is_ready(&self) -> bool {
+        self.count >= self.config.kind.warmup()
+    }
+}
+
+/// 設定された指標群を保持し、tick/確定足毎に更新する
+#[derive(Debug, Clone, Default)]
+pub struct Indicators {
+    items: Vec<Indicator>,
+}
+
+impl Indicators {
+    pub fn new() -> Self {
+        Indicators::default()
+    }
+
+    // 指標を追加する、同名の指標は置き換える
+    pub fn add(&mut self, config: IndicatorConfig) -> Result<(), SignalError> {
+        let indicator = Indicator::new(config)?;
+        self.items.retain(|i| i.config.name != indicator.config.name);
+        self.items.push(indicator);
+        Ok(())
+    }
+
+    pub fn configs(&self) -> impl Iterator<Item = &IndicatorConfig> {
+        self.items.iter().map(|i| &i.config)
+    }
+
+    // CORE: tick毎にmid値で更新する
+    pub fn on_tick(&mut self, t: &Ticker) {
+        let Some(mid) = t.mid().to_f64() else {
+            return;
+        };
+        for i in self.items.iter_mut() {
+            if i.config.source == IndicatorSource::Tick {
+                let value = i.engine.next_price(mid);
+                i.update(value);
+            }
+        }
+    }
+
+    // CORE: 足の確定毎にmidのOHLCで更新する
+    pub fn on_bar(&mut self, interval: BarInterval, bar: &Bar) {
+        let item = DataItem::builder()
+            .open(bar.mid.open.to_f64().unwrap_or_default())
+            .high(bar.mid.high.to_f64().unwrap_or_default())
+            .low(bar.mid.low.to_f64().unwrap_or_default())
+            .close(bar.mid.close.to_f64().unwrap_or_default())
+            .volume(bar.ticks as f64)
+            .build();
+        let Ok(item) = item else {
+            return;
+        };
+
+        for i in self.items.iter_mut() {
+            if i.config.source == IndicatorSource::Bar(interval) {
+                let value = i.engine.next_item(&item);
+                i.update(value);
+            }
+        }
+    }
+
+    // 指標値を取得する、必要な入力数に達するまではNone
+    pub fn get(&self, name: &str) -> Option<IndicatorValue> {
+        self.items
+            .iter()
+            .find(|i| i.config.name == name)
+            .filter(|i| i.is_ready())
+            .and_then(|i| i.value)
+    }
+
+    pub fn values(&self) -> impl Iterator<Item = (&str, IndicatorValue)> {
+        self.items
+            .iter()
+            .filter(|i| i.is_ready())
+            .filter_map(|i| i.value.map(|v| (i.config.name.as_str(), v)))
+    }
+
+    pub fn len(&self) -> usize {
+        self.items.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.items.is_empty()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::Utc;
+    use rust_decimal::Decimal;
+
+    fn tick(price: i64) -> Ticker {
+        Ticker {
+            symbol: "USDJPY".to_string(),
+            bid: Decimal::from(price),
+            ask: Decimal::from(price),
+            recived_at: Some(Utc::now()),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_indicators_invalid_period() {
+        let mut indicators = Indicators::new();
+        let result = indicators.add(IndicatorConfig::new("sma", IndicatorKind::Sma(0), IndicatorSource::Tick));
+        assert!(matches!(result, Err(SignalError::Indicator(_))));
+        assert!(indicators.is_empty());
+    }
+
+    #[test]
+    fn test_indicators_sma_warmup() {
+        let mut indicators = Indicators::new();
+        indicators
+            .add(IndicatorConfig::new("sma3", IndicatorKind::Sma(3), IndicatorSource::Tick))
+            .unwrap();
+
+        indicators.on_tick(&tick(1));
+        indicators.on_tick(&tick(2));
+        assert!(indicators.get("sma3").is_none());
+
+        indicators.on_tick(&tick(6));
+        assert_eq!(indicators.get("sma3").unwrap().value(), 3.0);
+        assert_eq!(indicators.values().count(), 1);
+    }
+
+    #[test]
+    fn test_indicators_replace_same_name() {
+        let mut indicators = Indicators::new();
+        indicators
+            .add(IndicatorConfig::new("ma", IndicatorKind::Sma(3), IndicatorSource::Tick))
+            .unwrap();
+        indicators
+            .add(IndicatorConfig::new("ma", IndicatorKind::Ema(5), IndicatorSource::Tick))
+            .unwrap();
+        assert_eq!(indicators.len(), 1);
+        assert_eq!(indicators.configs().next().unwrap().kind, IndicatorKind::Ema(5));
+    }
+
+    #[test]
+    fn test_indicators_bollinger_and_macd() {
+        let mut indicators = Indicators::new();
+        indicators
+            .add(IndicatorConfig::new("bb", IndicatorKind::Bollinger(5, 2.0), IndicatorSource::Tick))
+            .unwrap();
+        indicators
+            .add(IndicatorConfig::new("macd", IndicatorKind::Macd(3, 6, 3), IndicatorSource::Tick))
+            .unwrap();
+        for p in [100, 101, 103, 102, 104, 106, 105, 107, 109, 108] {
+            indicators.on_tick(&tick(p));
+        }
+
+        match indicators.get("bb").unwrap() {
+            IndicatorValue::Bands { upper, average, lower } => {
+                assert!(upper > average && average > lower);
+            }
+            v => panic!("unexpected value: {:?}", v),
+        }
+        match indicators.get("macd").unwrap() {
+            IndicatorValue::Macd { macd, .. } => assert!(macd > 0.0),
+            v => panic!("unexpected value: {:?}", v),
+        }
+    }
+
+    #[test]
+    fn test_indicators_bar_source_ignores_ticks() {
+        let mut indicators = Indicators::new();
+        indicators
+            .add(IndicatorConfig::new("atr", IndicatorKind::Atr(2), IndicatorSource::Bar(BarInterval::Ticks(2))))
+            .unwrap();
+        indicators.on_tick(&tick(100));
+        indicators.on_tick(&tick(110));
+        assert!(indicators.get("atr").is_none());
+
+        let mut bar = Bar::new(&tick(100), Utc::now());
+        bar.update(&tick(104), Utc::now());
+        indicators.on_bar(BarInterval::Ticks(2), &bar);
+        indicators.on_bar(BarInterval::Ticks(2), &bar);
+        // 別の足の確定は無視する
+        indicators.on_bar(BarInterval::S1, &bar);
+        assert!(indicators.get("atr").unwrap().value() > 0.0);
+    }
+}
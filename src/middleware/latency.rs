@@ -0,0 +1,242 @@
+use std::collections::VecDeque;
+
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    consts::{DEFAULT_LATENCY_WINDOW, DEFAULT_SKEW_TOLERANCE_MICROS},
+    error::SignalError,
+    middleware::ticker::Ticker,
+};
+
+/// 受信遅延の集計結果、Statusに表示する
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub struct LatencySnapshot {
+    pub count: usize,
+    pub p50: i64,
+    pub p95: i64,
+    pub p99: i64,
+    pub min: i64,
+    pub max: i64,
+    // 推定した時計のずれ (micros)、許容範囲内の場合はNone
+    pub skew_micros: Option<i64>,
+    // 遅延上限を超えたtick数
+    pub stale: u64,
+}
+
+impl LatencySnapshot {
+    // 時計のずれを差し引いた遅延 (micros)
+    pub fn latency_of(&self, t: &Ticker) -> Option<i64> {
+        t.diff_micros
+            .map(|diff| diff.saturating_sub(self.skew_micros.unwrap_or(0)))
+    }
+
+    // 遅延上限を超えているか、遅延が不明なtickは判定しない
+    pub fn is_stale(&self, t: &Ticker, limit_micros: i64) -> bool {
+        self.latency_of(t).is_some_and(|latency| latency > limit_micros)
+    }
+}
+
+/// Ticker::diff_microsを集計し、遅延のパーセンタイルと時計のずれを監視する
+/// 時計のずれはウィンドウ内の最小遅延で推定する
+/// 最小遅延が許容値を超えて負の場合のみ時計のずれとみなす、正の遅延は継続していても遅延として扱う
+#[derive(Debug, Clone)]
+pub struct LatencyMonitor {
+    window: usize,
+    skew_tolerance_micros: i64,
+    samples: VecDeque<i64>,
+    stale: u64,
+}
+
+impl Default for LatencyMonitor {
+    fn default() -> Self {
+        LatencyMonitor::new(DEFAULT_LATENCY_WINDOW)
+    }
+}
+
+impl LatencyMonitor {
+    pub fn new(window: usize) -> Self {
+        LatencyMonitor {
+            window: window.max(1),
+            skew_tolerance_micros: DEFAULT_SKEW_TOLERANCE_MICROS,
+            samples: VecDeque::new(),
+            stale: 0,
+        }
+    }
+
+    pub fn set_skew_tolerance(&mut self, micros: i64) {
+        self.skew_tolerance_micros = micros.abs();
+    }
+
+    pub fn len(&self) -> usize {
+        self.samples.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.samples.is_empty()
+    }
+
+    // 推定した時計のずれ (micros)
+    pub fn skew_micros(&self) -> Option<i64> {
+        let min = *self.samples.iter().min()?;
+        (min < -self.skew_tolerance_micros).then_some(min)
+    }
+
+    // CORE: tickの遅延を記録し、上限を超えていればStaleTickを返す
+    // diff_microsを持たないtickは記録しない
+    pub fn observe(&mut self, t: &Ticker, limit_micros: Option<i64>) -> Result<(), SignalError> {
+        let Some(diff) = t.diff_micros else {
+            return Ok(());
+        };
+        self.samples.push_back(diff);
+        while self.samples.len() > self.window {
+            self.samples.pop_front();
+        }
+
+        match limit_micros {
+            Some(limit_micros) => self.check(t, limit_micros),
+            None => Ok(()),
+        }
+    }
+
+    // 時計のずれを差し引いた遅延が上限を超えていればStaleTickを返し、件数に加える
+    // 記録はしないため、observe済みのtickを注文判定の前に検査する場合に使用する
+    pub fn check(&mut self, t: &Ticker, limit_micros: i64) -> Result<(), SignalError> {
+        let Some(diff) = t.diff_micros else {
+            return Ok(());
+        };
+        let latency_micros = diff.saturating_sub(self.skew_micros().unwrap_or(0));
+        if latency_micros > limit_micros {
+            self.stale += 1;
+            return Err(SignalError::StaleTick {
+                latency_micros,
+                limit_micros,
+            });
+        }
+        Ok(())
+    }
+
+    pub fn snapshot(&self) -> LatencySnapshot {
+        let mut sorted: Vec<i64> = self.samples.iter().copied().collect();
+        sorted.sort_unstable();
+
+        LatencySnapshot {
+            count: sorted.len(),
+            p50: percentile(&sorted, 50),
+            p95: percentile(&sorted, 95),
+            p99: percentile(&sorted, 99),
+            min: sorted.first().copied().unwrap_or(0),
+            max: sorted.last().copied().unwrap_or(0),
+            skew_micros: self.skew_micros(),
+            stale: self.stale,
+        }
+    }
+}
+
+// ソート済み配列のパーセンタイル (nearest-rank)
+fn percentile(sorted: &[i64], p: usize) -> i64 {
+    if sorted.is_empty() {
+        return 0;
+    }
+    let rank = (p * sorted.len()).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tick(diff_micros: i64) -> Ticker {
+        Ticker {
+            diff_micros: Some(diff_micros),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_latency_percentiles() {
+        let mut monitor = LatencyMonitor::new(100);
+        for i in 1..=100 {
+            monitor.observe(&tick(i * 1_000), None).unwrap();
+        }
+        let s = monitor.snapshot();
+        assert_eq!(s.count, 100);
+        assert_eq!((s.p50, s.p95, s.p99), (50_000, 95_000, 99_000));
+        assert_eq!((s.min, s.max), (1_000, 100_000));
+        assert_eq!(s.skew_micros, None);
+
+        // ウィンドウを超えた古い値は除外する
+        monitor.observe(&tick(500_000), None).unwrap();
+        let s = monitor.snapshot();
+        assert_eq!(s.count, 100);
+        assert_eq!((s.min, s.max), (2_000, 500_000));
+    }
+
+    #[test]
+    fn test_latency_ignores_missing_diff() {
+        let mut monitor = LatencyMonitor::default();
+        assert!(monitor.observe(&Ticker::default(), Some(0)).is_ok());
+        assert!(monitor.is_empty());
+        assert_eq!(monitor.snapshot(), LatencySnapshot::default());
+    }
+
+    #[test]
+    fn test_latency_skew_detection() {
+        let mut monitor = LatencyMonitor::new(10);
+        // サーバー時刻が進んでいるため負の遅延となる
+        for diff in [-300_000, -298_000, -295_000] {
+            monitor.observe(&tick(diff), None).unwrap();
+        }
+        let s = monitor.snapshot();
+        assert_eq!(s.skew_micros, Some(-300_000));
+        // ずれを差し引いた遅延で判定する
+        assert_eq!(s.latency_of(&tick(-290_000)), Some(10_000));
+        assert!(!s.is_stale(&tick(-290_000), 50_000));
+        assert!(s.is_stale(&tick(-200_000), 50_000));
+        assert!(!s.is_stale(&Ticker::default(), 0));
+    }
+
+    #[test]
+    fn test_latency_stale_guard() {
+        let mut monitor = LatencyMonitor::new(10);
+        assert!(monitor.observe(&tick(5_000), Some(20_000)).is_ok());
+        let result = monitor.observe(&tick(30_000), Some(20_000));
+        assert!(matches!(
+            result,
+            Err(SignalError::StaleTick {
+                latency_micros: 30_000,
+                limit_micros: 20_000
+            })
+        ));
+        assert_eq!(monitor.snapshot().stale, 1);
+
+        // checkは記録せず判定と件数のみ
+        assert!(monitor.check(&tick(30_000), 20_000).is_err());
+        assert!(monitor.check(&Ticker::default(), 0).is_ok());
+        assert_eq!((monitor.len(), monitor.snapshot().stale), (2, 2));
+    }
+
+    #[test]
+    fn test_latency_sustained_delay_is_stale() {
+        // 継続した500msの遅延は時計のずれではない
+        let mut monitor = LatencyMonitor::new(10);
+        for _ in 0..10 {
+            let result = monitor.observe(&tick(500_000), Some(100_000));
+            assert!(matches!(
+                result,
+                Err(SignalError::StaleTick {
+                    latency_micros: 500_000,
+                    limit_micros: 100_000
+                })
+            ));
+        }
+        let s = monitor.snapshot();
+        assert_eq!(s.skew_micros, None);
+        assert_eq!(s.stale, 10);
+        assert!(s.is_stale(&tick(500_000), 100_000));
+
+        // 許容値内の負の遅延もずれとはみなさない
+        let mut monitor = LatencyMonitor::new(10);
+        monitor.observe(&tick(-10_000), None).unwrap();
+        assert_eq!(monitor.skew_micros(), None);
+    }
+}
@@ -1,5 +1,13 @@
+pub mod command;
 pub mod file;
+pub mod indicator;
+pub mod latency;
 pub mod mouse;
+pub mod recorder;
+pub mod replay;
+pub mod rolling;
+pub mod sanitizer;
+pub mod synthetic;
 pub mod tcp;
 pub mod ticker;
 pub mod utils;
@@ -0,0 +1,372 @@
+use std::{
+    fs::{self, File, OpenOptions},
+    io::{BufWriter, Write},
+    path::{Path, PathBuf},
+};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use log::info;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::{error::SignalError, middleware::ticker::Ticker};
+
+/// 記録形式
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum RecordFormat {
+    #[default]
+    Csv,
+    Jsonl,
+}
+
+impl RecordFormat {
+    pub fn extension(&self) -> &'static str {
+        match self {
+            RecordFormat::Csv => "csv",
+            RecordFormat::Jsonl => "jsonl",
+        }
+    }
+
+    // 拡張子から形式を判定する
+    pub fn from_path(path: &Path) -> Option<Self> {
+        match path.extension()?.to_str()? {
+            "csv" => Some(RecordFormat::Csv),
+            "jsonl" | "json" => Some(RecordFormat::Jsonl),
+            _ => None,
+        }
+    }
+}
+
+/// CSVの1行、csvはネストした構造体を扱えないため注文指示はJSON文字列の列とする
+/// 列はTickerと同じ並びで、commandがない場合は空欄
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct TickerRow {
+    pub symbol: String,
+    pub bid: Decimal,
+    pub ask: Decimal,
+    pub flag: Option<u8>,
+    pub side: Option<u8>,
+    #[serde(default)]
+    pub command: Option<String>,
+    pub server_at: Option<DateTime<Utc>>,
+    pub recived_at: Option<DateTime<Utc>>,
+    pub diff_micros: Option<i64>,
+}
+
+impl TickerRow {
+    pub fn from_ticker(t: &Ticker) -> Result<Self, serde_json::Error> {
+        Ok(TickerRow {
+            symbol: t.symbol.clone(),
+            bid: t.bid,
+            ask: t.ask,
+            flag: t.flag,
+            side: t.side,
+            command: t.command.as_ref().map(serde_json::to_string).transpose()?,
+            server_at: t.server_at,
+            recived_at: t.recived_at,
+            diff_micros: t.diff_micros,
+        })
+    }
+
+    pub fn into_ticker(self) -> Result<Ticker, serde_json::Error> {
+        Ok(Ticker {
+            symbol: self.symbol,
+            bid: self.bid,
+            ask: self.ask,
+            flag: self.flag,
+            side: self.side,
+            command: self
+                .command
+                .filter(|c| !c.trim().is_empty())
+                .map(|c| serde_json::from_str(&c))
+                .transpose()?,
+            server_at: self.server_at,
+            recived_at: self.recived_at,
+            diff_micros: self.diff_micros,
+        })
+    }
+}
+
+/// ファイルの切り替え条件
+/// Daily: tickの日付 (UTC) が変わったとき、Size: 指定バイト数を超えるとき
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum Rotation {
+    #[default]
+    Daily,
+    Size(u64),
+}
+
+// 書き込み中のファイル
+#[derive(Debug)]
+struct Current {
+    path: PathBuf,
+    writer: BufWriter<File>,
+    date: NaiveDate,
+    written: u64,
+}
+
+/// 受信した全tickをファイルへ追記する
+/// ファイル名は `{prefix}_{YYYYMMDD}_{連番}.{拡張子}`、既存ファイルがあれば追記する
+#[derive(Debug)]
+pub struct Recorder {
+    dir: PathBuf,
+    prefix: String,
+    format: RecordFormat,
+    rotation: Rotation,
+    seq: u32,
+    current: Option<Current>,
+}
+
+impl Recorder {
+    pub fn new(dir: impl Into<PathBuf>, format: RecordFormat, rotation: Rotation) -> Self {
+        Recorder {
+            dir: dir.into(),
+            prefix: "ticks".to_string(),
+            format,
+            rotation,
+            seq: 0,
+            current: None,
+        }
+    }
+
+    pub fn with_prefix(mut self, prefix: &str) -> Self {
+        self.prefix = prefix.to_string();
+        self
+    }
+
+    // 書き込み中のファイル
+    pub fn path(&self) -> Option<&Path> {
+        self.current.as_ref().map(|c| c.path.as_path())
+    }
+
+    // CORE: tickを1行として追記する、必要に応じてファイルを切り替える
+    pub fn record(&mut self, t: &Ticker) -> Result<(), SignalError> {
+        let date = t.timestamp().unwrap_or_else(Utc::now).date_naive();
+
+        let line = self.encode(t, false)?;
+        let should_rotate = match (&self.current, self.rotation) {
+            (None, _) => true,
+            (Some(c), Rotation::Daily) => c.date != date,
+            (Some(c), Rotation::Size(max)) => c.written > 0 && c.written + line.len() as u64 > max,
+        };
+        if should_rotate {
+            self.open(date, line.len() as u64)?;
+        }
+
+        // 新規ファイルのみヘッダーを書き込む
+        let is_new = self.current.as_ref().is_some_and(|c| c.written == 0);
+        let line = if is_new && self.format == RecordFormat::Csv {
+            self.encode(t, true)?
+        } else {
+            line
+        };
+        if let Some(current) = self.current.as_mut() {
+            current.writer.write_all(&line)?;
+            current.written += line.len() as u64;
+        }
+        Ok(())
+    }
+
+    pub fn flush(&mut self) -> Result<(), SignalError> {
+        if let Some(current) = self.current.as_mut() {
+            current.writer.flush()?;
+        }
+        Ok(())
+    }
+
+    fn encode(&self, t: &Ticker, header: bool) -> Result<Vec<u8>, SignalError> {
+        match self.format {
+            RecordFormat::Csv => {
+                let row = TickerRow::from_ticker(t).map_err(|e| SignalError::Parse(e.to_string()))?;
+                let mut w = csv::WriterBuilder::new().has_headers(header).from_writer(vec![]);
+                w.serialize(row).map_err(|e| SignalError::Parse(e.to_string()))?;
+                w.into_inner().map_err(|e| SignalError::Parse(e.to_string()))
+            }
+            RecordFormat::Jsonl => {
+                let mut line = serde_json::to_vec(t).map_err(|e| SignalError::Parse(e.to_string()))?;
+                line.push(b'\n');
+                Ok(line)
+            }
+        }
+    }
+
+    fn file_path(&self, date: NaiveDate, seq: u32) -> PathBuf {
+        self.dir.join(format!(
+            "{}_{}_{:03}.{}",
+            self.prefix,
+            date.format("%Y%m%d"),
+            seq,
+            self.format.extension()
+        ))
+    }
+
+    // 日付が変われば連番を戻し、サイズ上限に達した既存ファイルは飛ばして開く
+    fn open(&mut self, date: NaiveDate, next_len: u64) -> Result<(), SignalError> {
+        if let Some(mut current) = self.current.take() {
+            current.writer.flush()?;
+            if current.date == date {
+                self.seq += 1;
+            } else {
+                self.seq = 0;
+            }
+        }
+        fs::create_dir_all(&self.dir)?;
+
+        let mut path = self.file_path(date, self.seq);
+        if let Rotation::Size(max) = self.rotation {
+            while fs::metadata(&path).is_ok_and(|m| m.len() > 0 && m.len() + next_len > max) {
+                self.seq += 1;
+                path = self.file_path(date, self.seq);
+            }
+        }
+
+        let file = OpenOptions::new().create(true).append(true).open(&path)?;
+        let written = file.metadata()?.len();
+        info!("recording ticks to {}", path.display());
+        self.current = Some(Current {
+            path,
+            writer: BufWriter::new(file),
+            date,
+            written,
+        });
+        Ok(())
+    }
+}
+
+impl Drop for Recorder {
+    fn drop(&mut self) {
+        let _ = self.flush();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::command::{Action, Command, Side};
+    use chrono::TimeZone;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("signalrs_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    fn tick(at: DateTime<Utc>) -> Ticker {
+        let mut t = Ticker {
+            symbol: "USDJPY".to_string(),
+            bid: Decimal::new(150_123, 3),
+            ask: Decimal::new(150_125, 3),
+            server_at: Some(at - chrono::Duration::milliseconds(5)),
+            ..Default::default()
+        };
+        t.recived_at = Some(at);
+        t.diff_micros = Some(5_000);
+        t
+    }
+
+    fn files(dir: &Path) -> Vec<String> {
+        let mut names: Vec<String> = fs::read_dir(dir)
+            .unwrap()
+            .map(|e| e.unwrap().file_name().to_string_lossy().to_string())
+            .collect();
+        names.sort();
+        names
+    }
+
+    #[test]
+    fn test_recorder_csv_daily_rotation() {
+        let dir = temp_dir("recorder_csv");
+        let day1 = Utc.with_ymd_and_hms(2024, 1, 1, 23, 59, 59).unwrap();
+        let day2 = Utc.with_ymd_and_hms(2024, 1, 2, 0, 0, 1).unwrap();
+        {
+            let mut recorder = Recorder::new(&dir, RecordFormat::Csv, Rotation::Daily);
+            recorder.record(&tick(day1)).unwrap();
+            recorder.record(&tick(day1)).unwrap();
+            recorder.record(&tick(day2)).unwrap();
+        }
+        assert_eq!(files(&dir), vec!["ticks_20240101_000.csv", "ticks_20240102_000.csv"]);
+
+        let mut reader = csv::Reader::from_path(dir.join("ticks_20240101_000.csv")).unwrap();
+        let headers = reader.headers().unwrap().clone();
+        assert!(headers.iter().any(|h| h == "server_at"));
+        assert!(headers.iter().any(|h| h == "recived_at"));
+        assert!(headers.iter().any(|h| h == "diff_micros"));
+        let rows: Vec<Ticker> = reader.deserialize().map(|r| r.unwrap()).collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].recived_at, Some(day1));
+        assert_eq!(rows[0].diff_micros, Some(5_000));
+        assert_eq!(rows[0].bid, Decimal::new(150_123, 3));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_recorder_csv_command_roundtrip() {
+        let dir = temp_dir("recorder_csv_command");
+        let at = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let command = Command {
+            action: Action::Entry,
+            side: Some(Side::Buy),
+            volume: Some(2),
+            hold_ms: Some(5_000),
+            id: Some("ea-1".to_string()),
+        };
+        {
+            let mut recorder = Recorder::new(&dir, RecordFormat::Csv, Rotation::Daily);
+            recorder.record(&tick(at)).unwrap();
+            recorder
+                .record(&Ticker {
+                    command: Some(command.clone()),
+                    ..tick(at)
+                })
+                .unwrap();
+        }
+
+        // commandの有無で列数は変わらない
+        let mut reader = csv::Reader::from_path(dir.join("ticks_20240101_000.csv")).unwrap();
+        let rows: Vec<Ticker> = reader
+            .deserialize::<TickerRow>()
+            .map(|r| r.unwrap().into_ticker().unwrap())
+            .collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[0].command, None);
+        assert_eq!(rows[1].command, Some(command));
+        assert_eq!(rows[1].bid, Decimal::new(150_123, 3));
+        assert_eq!(rows[1].recived_at, Some(at));
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_recorder_jsonl_size_rotation() {
+        let dir = temp_dir("recorder_jsonl");
+        let at = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let line_len = serde_json::to_vec(&tick(at)).unwrap().len() as u64 + 1;
+        {
+            // 1ファイルに2行まで
+            let mut recorder = Recorder::new(&dir, RecordFormat::Jsonl, Rotation::Size(line_len * 2));
+            for _ in 0..5 {
+                recorder.record(&tick(at)).unwrap();
+            }
+            assert_eq!(recorder.path().unwrap().file_name().unwrap(), "ticks_20240101_002.jsonl");
+        }
+        assert_eq!(
+            files(&dir),
+            vec!["ticks_20240101_000.jsonl", "ticks_20240101_001.jsonl", "ticks_20240101_002.jsonl"]
+        );
+
+        let content = fs::read_to_string(dir.join("ticks_20240101_000.jsonl")).unwrap();
+        let rows: Vec<Ticker> = content.lines().map(|l| serde_json::from_str(l).unwrap()).collect();
+        assert_eq!(rows.len(), 2);
+        assert_eq!(rows[1].server_at, Some(at - chrono::Duration::milliseconds(5)));
+
+        // 再起動後は上限に達したファイルを飛ばして追記する
+        {
+            let mut recorder = Recorder::new(&dir, RecordFormat::Jsonl, Rotation::Size(line_len * 2));
+            recorder.record(&tick(at)).unwrap();
+            assert_eq!(recorder.path().unwrap().file_name().unwrap(), "ticks_20240101_002.jsonl");
+        }
+
+        fs::remove_dir_all(&dir).unwrap();
+    }
+}
@@ -0,0 +1,220 @@
+use std::{
+    fs::File,
+    io::{BufRead, BufReader},
+    path::PathBuf,
+    sync::mpsc::{Receiver, Sender},
+    thread::JoinHandle,
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use log::{error, info};
+use serde::de::Error as _;
+use serde_json::Error;
+
+use crate::{
+    error::SignalError,
+    middleware::{
+        recorder::{RecordFormat, TickerRow},
+        ticker::Ticker,
+    },
+};
+
+/// 再生速度
+/// RealTime: 記録時の間隔、Speed(n): n倍速、AsFastAsPossible: 待機なし
+#[derive(Debug, Clone, Copy, PartialEq, Default)]
+pub enum Pacing {
+    RealTime,
+    Speed(f64),
+    #[default]
+    AsFastAsPossible,
+}
+
+impl Pacing {
+    // 記録上の経過時間を再生上の経過時間に変換する、待機しない場合はNone
+    fn scale(&self, elapsed: chrono::Duration) -> Option<Duration> {
+        let speed = match *self {
+            Pacing::RealTime => 1.0,
+            Pacing::Speed(n) if n > 0.0 => n,
+            _ => return None,
+        };
+        let micros = elapsed.num_microseconds()?.max(0) as f64 / speed;
+        Some(Duration::from_micros(micros as u64))
+    }
+}
+
+/// 記録済みtick (CSV/JSONL) を読み込み、TcpClientと同じ型のチャネルへ送信する
+/// 記録時のrecived_atを保持したまま送信するため、受信側でculc_diff_microsを呼ぶと時刻が上書きされる点に注意
+pub struct ReplaySource {
+    tx: Sender<Result<Ticker, Error>>,
+    paths: Vec<PathBuf>,
+    pacing: Pacing,
+}
+
+impl ReplaySource {
+    pub fn new(paths: Vec<PathBuf>, pacing: Pacing) -> (Self, Receiver<Result<Ticker, Error>>) {
+        let (tx, rx) = std::sync::mpsc::channel();
+        (ReplaySource { tx, paths, pacing }, rx)
+    }
+
+    // CORE: 別スレッドで再生を開始する
+    // ファイルが開けない・形式が不明な場合は開始前にエラーを返す
+    pub fn start(&self) -> Result<JoinHandle<()>, SignalError> {
+        let mut sources = vec![];
+        for path in self.paths.iter() {
+            let format = RecordFormat::from_path(path)
+                .ok_or_else(|| SignalError::Parse(format!("unknown record format: {}", path.display())))?;
+            let file = File::open(path)?;
+            sources.push((format, BufReader::new(file)));
+        }
+
+        let tx = self.tx.clone();
+        let pacing = self.pacing;
+
+        Ok(std::thread::spawn(move || {
+            let started = Instant::now();
+            let mut origin: Option<DateTime<Utc>> = None;
+            let mut sent = 0usize;
+
+            for (format, reader) in sources {
+                for record in read_records(format, reader) {
+                    // 最初のtickからの経過時間に合わせて待機する
+                    if let Some(at) = record.as_ref().ok().and_then(|t| t.timestamp()) {
+                        let first = *origin.get_or_insert(at);
+                        if let Some(wait) = pacing.scale(at - first) {
+                            if let Some(remaining) = wait.checked_sub(started.elapsed()) {
+                                std::thread::sleep(remaining);
+                            }
+                        }
+                    }
+
+                    if tx.send(record).is_err() {
+                        info!("replay receiver closed after {} records", sent);
+                        return;
+                    }
+                    sent += 1;
+                }
+            }
+            info!("replay finished: {} records", sent);
+        }))
+    }
+}
+
+// 形式に応じて1件ずつ読み込む
+fn read_records(format: RecordFormat, reader: BufReader<File>) -> Box<dyn Iterator<Item = Result<Ticker, Error>>> {
+    match format {
+        RecordFormat::Csv => Box::new(
+            csv::Reader::from_reader(reader)
+                .into_deserialize::<TickerRow>()
+                .map(|r| {
+                    r.map_err(Error::custom)
+                        .and_then(TickerRow::into_ticker)
+                        .inspect_err(|e| error!("Failed to parse CSV: {}", e))
+                }),
+        ),
+        RecordFormat::Jsonl => Box::new(reader.lines().filter_map(|line| match line {
+            Ok(line) if line.trim().is_empty() => None,
+            Ok(line) => Some(serde_json::from_str::<Ticker>(&line).inspect_err(|e| {
+                error!("Failed to parse JSON: {}", e);
+            })),
+            Err(e) => {
+                error!("Failed to read record: {}", e);
+                Some(Err(Error::custom(e)))
+            }
+        })),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::recorder::{Recorder, Rotation};
+    use chrono::TimeZone;
+    use rust_decimal::Decimal;
+    use std::fs;
+
+    fn temp_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!("signalrs_{}_{}", name, std::process::id()));
+        let _ = fs::remove_dir_all(&dir);
+        dir
+    }
+
+    // 100ms間隔で記録する
+    fn record(dir: &PathBuf, format: RecordFormat, n: i64) -> PathBuf {
+        let base = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let mut recorder = Recorder::new(dir, format, Rotation::Daily);
+        for i in 0..n {
+            let at = base + chrono::Duration::milliseconds(i * 100);
+            recorder
+                .record(&Ticker {
+                    symbol: "USDJPY".to_string(),
+                    bid: Decimal::from(150 + i),
+                    ask: Decimal::from(150 + i),
+                    server_at: Some(at),
+                    recived_at: Some(at),
+                    diff_micros: Some(0),
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+        recorder.path().unwrap().to_path_buf()
+    }
+
+    #[test]
+    fn test_replay_csv_as_fast_as_possible() {
+        let dir = temp_dir("replay_csv");
+        let path = record(&dir, RecordFormat::Csv, 5);
+
+        let (source, rx) = ReplaySource::new(vec![path], Pacing::AsFastAsPossible);
+        source.start().unwrap().join().unwrap();
+        drop(source);
+
+        let tickers: Vec<Ticker> = rx.iter().map(|r| r.unwrap()).collect();
+        assert_eq!(tickers.len(), 5);
+        assert_eq!(tickers[4].bid, Decimal::from(154));
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_replay_jsonl_accelerated() {
+        let dir = temp_dir("replay_jsonl");
+        let path = record(&dir, RecordFormat::Jsonl, 5);
+        // 不正な行はErrとして送信する
+        let mut content = fs::read_to_string(&path).unwrap();
+        content.push_str("{broken\n");
+        fs::write(&path, content).unwrap();
+
+        // 記録上400msを4倍速で再生する
+        let (source, rx) = ReplaySource::new(vec![path], Pacing::Speed(4.0));
+        let started = Instant::now();
+        source.start().unwrap().join().unwrap();
+        let elapsed = started.elapsed();
+        drop(source);
+
+        let records: Vec<_> = rx.iter().collect();
+        assert_eq!(records.len(), 6);
+        assert!(records[..5].iter().all(|r| r.is_ok()));
+        assert!(records[5].is_err());
+        assert!(elapsed >= Duration::from_millis(100), "elapsed: {:?}", elapsed);
+        assert!(elapsed < Duration::from_millis(400), "elapsed: {:?}", elapsed);
+        fs::remove_dir_all(&dir).unwrap();
+    }
+
+    #[test]
+    fn test_replay_missing_file() {
+        let (source, _rx) = ReplaySource::new(vec![PathBuf::from("./not_found.csv")], Pacing::RealTime);
+        assert!(matches!(source.start(), Err(SignalError::Io(_))));
+
+        let (source, _rx) = ReplaySource::new(vec![PathBuf::from("./ticks.txt")], Pacing::RealTime);
+        assert!(matches!(source.start(), Err(SignalError::Parse(_))));
+    }
+
+    #[test]
+    fn test_pacing_scale() {
+        let elapsed = chrono::Duration::milliseconds(1_000);
+        assert_eq!(Pacing::RealTime.scale(elapsed), Some(Duration::from_millis(1_000)));
+        assert_eq!(Pacing::Speed(10.0).scale(elapsed), Some(Duration::from_millis(100)));
+        assert_eq!(Pacing::Speed(0.0).scale(elapsed), None);
+        assert_eq!(Pacing::AsFastAsPossible.scale(elapsed), None);
+    }
+}
@@ -0,0 +1,175 @@
+use std::collections::VecDeque;
+
+use rust_decimal::{Decimal, MathematicalOps};
+use serde::{Deserialize, Serialize};
+
+/// スライディングウィンドウの統計量を逐次更新する
+/// push/popの度に合計・二乗和・最小/最大を更新し、全件の再計算を行わない
+/// 最小/最大は単調キューで保持するため、償却O(1)で更新できる
+#[derive(Debug, Clone, Default, Deserialize, Serialize)]
+pub struct RollingStats {
+    count: usize,
+    sum: Decimal,
+    sum_sq: Decimal,
+    // 最古要素と次に追加する要素の通し番号
+    head: u64,
+    tail: u64,
+    mins: VecDeque<(u64, Decimal)>,
+    maxs: VecDeque<(u64, Decimal)>,
+}
+
+impl RollingStats {
+    pub fn new() -> Self {
+        RollingStats::default()
+    }
+
+    // 最新値を追加する
+    pub fn push(&mut self, v: Decimal) {
+        self.count += 1;
+        self.sum += v;
+        self.sum_sq += v * v;
+
+        while self.mins.back().is_some_and(|(_, x)| *x >= v) {
+            self.mins.pop_back();
+        }
+        self.mins.push_back((self.tail, v));
+
+        while self.maxs.back().is_some_and(|(_, x)| *x <= v) {
+            self.maxs.pop_back();
+        }
+        self.maxs.push_back((self.tail, v));
+
+        self.tail += 1;
+    }
+
+    // 最古値を取り除く、vはウィンドウから削除された値
+    pub fn pop(&mut self, v: Decimal) {
+        if self.count == 0 {
+            return;
+        }
+        self.count -= 1;
+        self.sum -= v;
+        self.sum_sq -= v * v;
+
+        if self.mins.front().is_some_and(|(i, _)| *i == self.head) {
+            self.mins.pop_front();
+        }
+        if self.maxs.front().is_some_and(|(i, _)| *i == self.head) {
+            self.maxs.pop_front();
+        }
+
+        self.head += 1;
+    }
+
+    pub fn count(&self) -> usize {
+        self.count
+    }
+
+    pub fn mean(&self) -> Option<Decimal> {
+        if self.count == 0 {
+            return None;
+        }
+        Some(self.sum / Decimal::from(self.count))
+    }
+
+    // 不偏分散 (n - 1)
+    pub fn variance(&self) -> Option<Decimal> {
+        if self.count < 2 {
+            return None;
+        }
+        let n = Decimal::from(self.count);
+        let variance = (self.sum_sq - self.sum * self.sum / n) / (n - Decimal::ONE);
+        Some(variance.max(Decimal::ZERO))
+    }
+
+    pub fn std(&self) -> Option<Decimal> {
+        self.variance().and_then(|v| v.sqrt())
+    }
+
+    pub fn min(&self) -> Option<Decimal> {
+        self.mins.front().map(|(_, v)| *v)
+    }
+
+    pub fn max(&self) -> Option<Decimal> {
+        self.maxs.front().map(|(_, v)| *v)
+    }
+
+    // 指定値のZスコア、標準偏差が0またはデータ不足の場合はNone
+    pub fn zscore(&self, v: Decimal) -> Option<Decimal> {
+        let mean = self.mean()?;
+        let std = self.std()?;
+        if std.is_zero() {
+            return None;
+        }
+        Some((v - mean) / std)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn d(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    #[test]
+    fn test_rolling_stats_empty() {
+        let r = RollingStats::new();
+        assert_eq!(r.count(), 0);
+        assert!(r.mean().is_none());
+        assert!(r.variance().is_none());
+        assert!(r.min().is_none());
+        assert!(r.max().is_none());
+    }
+
+    #[test]
+    fn test_rolling_stats_push() {
+        let mut r = RollingStats::new();
+        for v in ["2", "4", "4", "4", "5", "5", "7", "9"] {
+            r.push(d(v));
+        }
+        assert_eq!(r.count(), 8);
+        assert_eq!(r.mean().unwrap(), d("5"));
+        // 不偏分散: 32 / 7
+        assert_eq!(r.variance().unwrap(), d("32") / d("7"));
+        assert_eq!(r.min().unwrap(), d("2"));
+        assert_eq!(r.max().unwrap(), d("9"));
+    }
+
+    #[test]
+    fn test_rolling_stats_pop_matches_window() {
+        let values: Vec<Decimal> = ["5", "1", "9", "3", "7", "2", "8"].iter().map(|v| d(v)).collect();
+        let window = 3;
+        let mut r = RollingStats::new();
+
+        for (i, v) in values.iter().enumerate() {
+            r.push(*v);
+            if i >= window {
+                r.pop(values[i - window]);
+            }
+
+            let start = (i + 1).saturating_sub(window);
+            let slice = &values[start..=i];
+            assert_eq!(r.count(), slice.len());
+            assert_eq!(r.min().unwrap(), *slice.iter().min().unwrap());
+            assert_eq!(r.max().unwrap(), *slice.iter().max().unwrap());
+            let mean = slice.iter().sum::<Decimal>() / Decimal::from(slice.len());
+            assert_eq!(r.mean().unwrap(), mean);
+        }
+    }
+
+    #[test]
+    fn test_rolling_stats_zscore() {
+        let mut r = RollingStats::new();
+        r.push(d("100"));
+        r.push(d("100"));
+        // 標準偏差0
+        assert!(r.zscore(d("100")).is_none());
+
+        r.push(d("103"));
+        let z = r.zscore(d("103")).unwrap();
+        assert!(z > Decimal::ZERO);
+    }
+}
@@ -0,0 +1,320 @@
+use std::collections::{HashMap, VecDeque};
+
+use log::warn;
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::middleware::{rolling::RollingStats, ticker::Ticker};
+
+/// 異常tickの扱い
+/// Reject: 破棄する
+/// Quarantine: 急変tickを保留し、次のtickが同水準であれば本物の値動きとして受け入れる
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum SanitizeAction {
+    #[default]
+    Reject,
+    Quarantine,
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct SanitizerConfig {
+    pub reject_zero: bool,
+    pub reject_crossed: bool,
+    // 許容するスプレッドの上限
+    pub max_spread: Option<Decimal>,
+    // 直前の受理tickからのmid変化が、tick間変化の標準偏差の何倍を超えたら急変とみなすか
+    pub max_jump_sigma: Option<Decimal>,
+    pub sigma_window: usize,
+    pub min_samples: usize,
+    pub action: SanitizeAction,
+}
+
+impl Default for SanitizerConfig {
+    fn default() -> Self {
+        SanitizerConfig {
+            reject_zero: true,
+            reject_crossed: true,
+            max_spread: None,
+            max_jump_sigma: None,
+            sigma_window: 100,
+            min_samples: 20,
+            action: SanitizeAction::Reject,
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum RejectReason {
+    Zero,
+    Crossed,
+    Spread,
+    Jump,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Verdict {
+    Accept,
+    Reject(RejectReason),
+    Quarantine(RejectReason),
+}
+
+/// 除外件数、Statusに表示する
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub struct RejectCounts {
+    pub zero: u64,
+    pub crossed: u64,
+    pub spread: u64,
+    pub jump: u64,
+    // 保留後に本物の値動きとして受け入れた件数
+    pub released: u64,
+}
+
+impl RejectCounts {
+    fn count(&mut self, reason: RejectReason) {
+        match reason {
+            RejectReason::Zero => self.zero += 1,
+            RejectReason::Crossed => self.crossed += 1,
+            RejectReason::Spread => self.spread += 1,
+            RejectReason::Jump => self.jump += 1,
+        }
+    }
+
+    pub fn total(&self) -> u64 {
+        self.zero + self.crossed + self.spread + self.jump
+    }
+}
+
+pub struct Sanitized {
+    pub verdict: Verdict,
+    // TickerStatsへ渡すtick、保留tickが解放された場合は古い順に2件となる
+    pub accepted: Vec<Ticker>,
+}
+
+// シンボル毎の急変判定の状態
+#[derive(Debug, Clone, Default)]
+struct JumpState {
+    last_mid: Option<Decimal>,
+    diffs: RollingStats,
+    window: VecDeque<Decimal>,
+    pending: Option<Ticker>,
+}
+
+impl JumpState {
+    fn accept(&mut self, mid: Decimal, sigma_window: usize) {
+        if let Some(last) = self.last_mid {
+            let d = mid - last;
+            self.diffs.push(d);
+            self.window.push_back(d);
+            while self.window.len() > sigma_window {
+                if let Some(old) = self.window.pop_front() {
+                    self.diffs.pop(old);
+                }
+            }
+        }
+        self.last_mid = Some(mid);
+    }
+
+    // fromからmidへの変化が閾値を超えるか
+    fn is_jump(&self, from: Decimal, mid: Decimal, config: &SanitizerConfig, n_sigma: Decimal) -> bool {
+        if self.diffs.count() < config.min_samples {
+            return false;
+        }
+        match self.diffs.std() {
+            Some(std) if !std.is_zero() => (mid - from).abs() > n_sigma * std,
+            _ => false,
+        }
+    }
+}
+
+/// TickerStatsへ渡す前に異常tickを除外する
+#[derive(Debug, Clone, Default)]
+pub struct Sanitizer {
+    config: SanitizerConfig,
+    states: HashMap<String, JumpState>,
+    counts: RejectCounts,
+}
+
+impl Sanitizer {
+    pub fn new(config: SanitizerConfig) -> Self {
+        Sanitizer {
+            config,
+            states: HashMap::new(),
+            counts: RejectCounts::default(),
+        }
+    }
+
+    pub fn counts(&self) -> RejectCounts {
+        self.counts
+    }
+
+    // 価格のみで判定できる異常
+    fn check_quote(&self, t: &Ticker) -> Option<RejectReason> {
+        if self.config.reject_zero && (t.bid <= Decimal::ZERO || t.ask <= Decimal::ZERO) {
+            return Some(RejectReason::Zero);
+        }
+        if self.config.reject_crossed && t.bid > t.ask {
+            return Some(RejectReason::Crossed);
+        }
+        if let Some(max_spread) = self.config.max_spread {
+            if t.spread() > max_spread {
+                return Some(RejectReason::Spread);
+            }
+        }
+        None
+    }
+
+    // CORE: tickを検査し、TickerStatsへ渡すtickを返す
+    pub fn sanitize(&mut self, t: Ticker) -> Sanitized {
+        if let Some(reason) = self.check_quote(&t) {
+            warn!("rejected tick ({:?}): {:?}", reason, t);
+            self.counts.count(reason);
+            return Sanitized {
+                verdict: Verdict::Reject(reason),
+                accepted: vec![],
+            };
+        }
+
+        let config = &self.config;
+        let state = self.states.entry(t.symbol.clone()).or_default();
+        let mid = t.mid();
+
+        let n_sigma = match (config.max_jump_sigma, state.last_mid) {
+            (Some(n_sigma), Some(last)) if state.is_jump(last, mid, config, n_sigma) => n_sigma,
+            _ => {
+                // 通常のtick、保留中のtickは単発の急変として破棄する
+                state.pending = None;
+                state.accept(mid, config.sigma_window);
+                return Sanitized {
+                    verdict: Verdict::Accept,
+                    accepted: vec![t],
+                };
+            }
+        };
+
+        // 保留tickと同水準であれば水準訂正とみなして受け入れる
+        if let Some(pending) = state.pending.take() {
+            let pending_mid = pending.mid();
+            if !state.is_jump(pending_mid, mid, config, n_sigma) {
+                let mut accepted = vec![];
+                if config.action == SanitizeAction::Quarantine {
+                    self.counts.released += 1;
+                    state.accept(pending_mid, config.sigma_window);
+                    accepted.push(pending);
+                }
+                state.accept(mid, config.sigma_window);
+                accepted.push(t);
+                return Sanitized {
+                    verdict: Verdict::Accept,
+                    accepted,
+                };
+            }
+        }
+
+        warn!("jump tick ({} sigma): {:?}", n_sigma, t);
+        self.counts.count(RejectReason::Jump);
+        state.pending = Some(t);
+        let verdict = match config.action {
+            SanitizeAction::Reject => Verdict::Reject(RejectReason::Jump),
+            SanitizeAction::Quarantine => Verdict::Quarantine(RejectReason::Jump),
+        };
+        Sanitized {
+            verdict,
+            accepted: vec![],
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::str::FromStr;
+
+    fn tick(bid: &str, ask: &str) -> Ticker {
+        Ticker {
+            symbol: "USDJPY".to_string(),
+            bid: Decimal::from_str(bid).unwrap(),
+            ask: Decimal::from_str(ask).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    // 150.000を中心に±0.001で振動する
+    fn warm_up(s: &mut Sanitizer, n: usize) {
+        for i in 0..n {
+            let p = if i % 2 == 0 { "150.000" } else { "150.001" };
+            let r = s.sanitize(tick(p, p));
+            assert_eq!(r.verdict, Verdict::Accept);
+        }
+    }
+
+    fn jump_config(action: SanitizeAction) -> SanitizerConfig {
+        SanitizerConfig {
+            max_jump_sigma: Some(Decimal::from(10)),
+            min_samples: 10,
+            action,
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_sanitizer_rejects_bad_quotes() {
+        let mut s = Sanitizer::new(SanitizerConfig {
+            max_spread: Some(Decimal::from_str("0.05").unwrap()),
+            ..Default::default()
+        });
+        assert_eq!(s.sanitize(tick("0", "150.0")).verdict, Verdict::Reject(RejectReason::Zero));
+        assert_eq!(s.sanitize(tick("150.1", "150.0")).verdict, Verdict::Reject(RejectReason::Crossed));
+        assert_eq!(s.sanitize(tick("150.0", "150.1")).verdict, Verdict::Reject(RejectReason::Spread));
+        let r = s.sanitize(tick("150.00", "150.02"));
+        assert_eq!(r.verdict, Verdict::Accept);
+        assert_eq!(r.accepted.len(), 1);
+
+        let counts = s.counts();
+        assert_eq!((counts.zero, counts.crossed, counts.spread), (1, 1, 1));
+        assert_eq!(counts.total(), 3);
+    }
+
+    #[test]
+    fn test_sanitizer_rejects_single_spike() {
+        let mut s = Sanitizer::new(jump_config(SanitizeAction::Reject));
+        warm_up(&mut s, 20);
+
+        assert_eq!(s.sanitize(tick("151.0", "151.0")).verdict, Verdict::Reject(RejectReason::Jump));
+        // 元の水準に戻る
+        let r = s.sanitize(tick("150.000", "150.000"));
+        assert_eq!(r.verdict, Verdict::Accept);
+        assert_eq!(r.accepted.len(), 1);
+        assert_eq!(s.counts().jump, 1);
+    }
+
+    #[test]
+    fn test_sanitizer_quarantine_releases_level_shift() {
+        let mut s = Sanitizer::new(jump_config(SanitizeAction::Quarantine));
+        warm_up(&mut s, 20);
+
+        let r = s.sanitize(tick("151.000", "151.000"));
+        assert_eq!(r.verdict, Verdict::Quarantine(RejectReason::Jump));
+        assert!(r.accepted.is_empty());
+
+        // 同水準のtickが続けば保留tickも解放される
+        let r = s.sanitize(tick("151.001", "151.001"));
+        assert_eq!(r.verdict, Verdict::Accept);
+        assert_eq!(r.accepted.len(), 2);
+        assert_eq!(r.accepted[0].bid, Decimal::from_str("151.000").unwrap());
+        assert_eq!(s.counts().released, 1);
+
+        // 新しい水準が基準になる
+        assert_eq!(s.sanitize(tick("151.000", "151.000")).verdict, Verdict::Accept);
+    }
+
+    #[test]
+    fn test_sanitizer_jump_state_per_symbol() {
+        let mut s = Sanitizer::new(jump_config(SanitizeAction::Reject));
+        warm_up(&mut s, 20);
+
+        // 別シンボルの価格水準は急変とみなさない
+        let mut eurusd = tick("1.08000", "1.08002");
+        eurusd.symbol = "EURUSD".to_string();
+        assert_eq!(s.sanitize(eurusd).verdict, Verdict::Accept);
+    }
+}
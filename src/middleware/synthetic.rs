@@ -0,0 +1,292 @@
+use std::{
+    io::Write,
+    net::{TcpListener, TcpStream},
+    time::{Duration, Instant},
+};
+
+use chrono::{DateTime, Utc};
+use log::{info, warn};
+use rand::{rngs::StdRng, Rng, SeedableRng};
+use rust_decimal::{prelude::FromPrimitive, Decimal};
+use serde::{Deserialize, Serialize};
+
+use crate::{error::SignalError, middleware::ticker::Ticker};
+
+/// 価格の生成モデル、変動幅はいずれも1tickあたり
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub enum PriceModel {
+    // 価格差が正規分布 (標準偏差step) に従う
+    RandomWalk { step: f64 },
+    // 幾何ブラウン運動、対数収益率の平均drift・標準偏差volatility
+    Gbm { drift: f64, volatility: f64 },
+    // 平常時と高ボラティリティ時を確率switch_probabilityで切り替える
+    RegimeSwitch {
+        calm: f64,
+        volatile: f64,
+        switch_probability: f64,
+    },
+    // GBMに確率jump_probabilityで標準偏差jump_sizeの跳躍を加える
+    JumpDiffusion {
+        volatility: f64,
+        jump_probability: f64,
+        jump_size: f64,
+    },
+}
+
+impl Default for PriceModel {
+    fn default() -> Self {
+        PriceModel::RandomWalk { step: 0.002 }
+    }
+}
+
+impl PriceModel {
+    // 名称と変動幅から各モデルの既定値で生成する
+    pub fn from_name(name: &str, volatility: f64) -> Option<Self> {
+        match name {
+            "walk" | "random-walk" => Some(PriceModel::RandomWalk { step: volatility }),
+            "gbm" => Some(PriceModel::Gbm { drift: 0.0, volatility }),
+            "regime" | "regime-switch" => Some(PriceModel::RegimeSwitch {
+                calm: volatility,
+                volatile: volatility * 5.0,
+                switch_probability: 0.01,
+            }),
+            "jump" | "jump-diffusion" => Some(PriceModel::JumpDiffusion {
+                volatility,
+                jump_probability: 0.005,
+                jump_size: volatility * 20.0,
+            }),
+            _ => None,
+        }
+    }
+}
+
+/// 標準正規分布 (Box-Muller)
+pub fn standard_normal<R: Rng>(rng: &mut R) -> f64 {
+    let u1: f64 = rng.gen_range(f64::EPSILON..1.0);
+    let u2: f64 = rng.gen();
+    (-2.0 * u1.ln()).sqrt() * (2.0 * std::f64::consts::PI * u2).cos()
+}
+
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct SyntheticConfig {
+    pub symbol: String,
+    // 初期のbid
+    pub price: f64,
+    pub model: PriceModel,
+    // ask - bid
+    pub spread: Decimal,
+    // 価格の小数桁数
+    pub digits: u32,
+    // 1秒あたりのtick数
+    pub rate: f64,
+    // 各tickに確率flag_probabilityでflagsのいずれかを付与する
+    pub flags: Vec<u8>,
+    pub flag_probability: f64,
+    pub seed: u64,
+}
+
+impl Default for SyntheticConfig {
+    fn default() -> Self {
+        SyntheticConfig {
+            symbol: "USDJPY".to_string(),
+            price: 150.0,
+            model: PriceModel::default(),
+            spread: Decimal::new(2, 3),
+            digits: 3,
+            rate: 10.0,
+            flags: vec![],
+            flag_probability: 0.0,
+            seed: 0,
+        }
+    }
+}
+
+/// MT5のEAの代わりにtickを生成する
+/// server_atはstartから1/rate秒ずつ進めるため、同じシードなら同じ系列になる
+pub struct SyntheticFeed {
+    config: SyntheticConfig,
+    rng: StdRng,
+    price: f64,
+    volatile: bool,
+    at: DateTime<Utc>,
+}
+
+impl SyntheticFeed {
+    pub fn new(config: SyntheticConfig, start: DateTime<Utc>) -> Self {
+        let rng = StdRng::seed_from_u64(config.seed);
+        let price = config.price;
+        SyntheticFeed {
+            config,
+            rng,
+            price,
+            volatile: false,
+            at: start,
+        }
+    }
+
+    pub fn interval(&self) -> Duration {
+        if self.config.rate > 0.0 {
+            Duration::from_secs_f64(1.0 / self.config.rate)
+        } else {
+            Duration::ZERO
+        }
+    }
+
+    fn step(&mut self) {
+        let z = standard_normal(&mut self.rng);
+        self.price = match self.config.model {
+            PriceModel::RandomWalk { step } => self.price + step * z,
+            PriceModel::Gbm { drift, volatility } => self.price * (drift - volatility * volatility / 2.0 + volatility * z).exp(),
+            PriceModel::RegimeSwitch {
+                calm,
+                volatile,
+                switch_probability,
+            } => {
+                if self.rng.gen_bool(switch_probability.clamp(0.0, 1.0)) {
+                    self.volatile = !self.volatile;
+                }
+                let volatility = if self.volatile { volatile } else { calm };
+                self.price * (volatility * z).exp()
+            }
+            PriceModel::JumpDiffusion {
+                volatility,
+                jump_probability,
+                jump_size,
+            } => {
+                let mut r = volatility * z;
+                if self.rng.gen_bool(jump_probability.clamp(0.0, 1.0)) {
+                    r += jump_size * standard_normal(&mut self.rng);
+                }
+                self.price * r.exp()
+            }
+        };
+        // 価格は正の値に保つ
+        let min = 10f64.powi(-(self.config.digits as i32));
+        self.price = self.price.max(min);
+    }
+}
+
+impl Iterator for SyntheticFeed {
+    type Item = Ticker;
+
+    fn next(&mut self) -> Option<Ticker> {
+        self.step();
+        let bid = Decimal::from_f64(self.price)?.round_dp(self.config.digits);
+        let flag = if !self.config.flags.is_empty() && self.rng.gen_bool(self.config.flag_probability.clamp(0.0, 1.0)) {
+            Some(self.config.flags[self.rng.gen_range(0..self.config.flags.len())])
+        } else {
+            None
+        };
+
+        let t = Ticker {
+            symbol: self.config.symbol.clone(),
+            bid,
+            ask: bid + self.config.spread,
+            flag,
+            server_at: Some(self.at),
+            ..Default::default()
+        };
+        self.at += chrono::Duration::from_std(self.interval()).unwrap_or_default();
+        Some(t)
+    }
+}
+
+// CORE: tickをJSON Linesとして書き込む、rate通りに待機し、limit件で終了する
+pub fn emit<W: Write>(feed: SyntheticFeed, writer: &mut W, limit: Option<usize>) -> Result<usize, SignalError> {
+    let interval = feed.interval();
+    let started = Instant::now();
+    let mut sent = 0usize;
+    for t in feed.take(limit.unwrap_or(usize::MAX)) {
+        let line = serde_json::to_string(&t).map_err(|e| SignalError::Parse(e.to_string()))?;
+        writeln!(writer, "{}", line)?;
+        writer.flush()?;
+        sent += 1;
+
+        if let Some(remaining) = interval.mul_f64(sent as f64).checked_sub(started.elapsed()) {
+            std::thread::sleep(remaining);
+        }
+    }
+    Ok(sent)
+}
+
+// TcpClient::received_server へ接続して送信する
+pub fn connect(addr: &str, feed: SyntheticFeed, limit: Option<usize>) -> Result<usize, SignalError> {
+    let mut stream = TcpStream::connect(addr)?;
+    info!("synthetic feed connected to {}", addr);
+    emit(feed, &mut stream, limit)
+}
+
+// 待ち受けて最初の接続 (TcpClient::connect) へ送信する
+pub fn serve(addr: &str, feed: SyntheticFeed, limit: Option<usize>) -> Result<usize, SignalError> {
+    let listener = TcpListener::bind(addr).map_err(|e| SignalError::TcpBind {
+        addr: addr.to_string(),
+        source: e,
+    })?;
+    info!("synthetic feed listening on {}", addr);
+    let (mut stream, peer) = listener.accept()?;
+    info!("synthetic feed accepted {}", peer);
+    emit(feed, &mut stream, limit).inspect_err(|e| warn!("synthetic feed stopped: {}", e))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::tcp::TcpClient;
+    use chrono::TimeZone;
+
+    fn start() -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()
+    }
+
+    #[test]
+    fn test_synthetic_feed_models() {
+        for name in ["walk", "gbm", "regime", "jump"] {
+            let config = SyntheticConfig {
+                model: PriceModel::from_name(name, 0.001).unwrap(),
+                ..Default::default()
+            };
+            let ticks: Vec<Ticker> = SyntheticFeed::new(config.clone(), start()).take(500).collect();
+            assert!(ticks.iter().all(|t| t.bid > Decimal::ZERO && t.spread() == config.spread), "{}", name);
+            assert!(ticks.iter().all(|t| t.bid.scale() <= 3));
+            // 10tick/秒で時刻が進む
+            assert_eq!(ticks[10].server_at.unwrap() - ticks[0].server_at.unwrap(), chrono::Duration::seconds(1));
+            // 同じシードなら同じ系列
+            let again: Vec<Decimal> = SyntheticFeed::new(config, start()).take(500).map(|t| t.bid).collect();
+            assert_eq!(ticks.iter().map(|t| t.bid).collect::<Vec<_>>(), again);
+        }
+        assert!(PriceModel::from_name("unknown", 0.001).is_none());
+    }
+
+    #[test]
+    fn test_synthetic_feed_flags() {
+        let config = SyntheticConfig {
+            flags: vec![1, 2],
+            flag_probability: 0.5,
+            ..Default::default()
+        };
+        let ticks: Vec<Ticker> = SyntheticFeed::new(config, start()).take(200).collect();
+        let flagged = ticks.iter().filter(|t| t.flag.is_some()).count();
+        assert!(flagged > 50 && flagged < 150);
+        assert!(ticks.iter().filter_map(|t| t.flag).all(|f| f == 1 || f == 2));
+    }
+
+    #[test]
+    fn test_synthetic_feed_to_tcp_client() {
+        let addr = "127.0.0.1:28765".to_string();
+        let (client, rx) = TcpClient::<Ticker>::new(addr.clone());
+        client.received_server().unwrap();
+
+        let config = SyntheticConfig {
+            rate: 1_000.0,
+            ..Default::default()
+        };
+        let sent = connect(&addr, SyntheticFeed::new(config, start()), Some(20)).unwrap();
+        assert_eq!(sent, 20);
+
+        let received: Vec<Ticker> = (0..20)
+            .map(|_| rx.recv_timeout(Duration::from_secs(1)).unwrap().unwrap())
+            .collect();
+        assert_eq!(received.len(), 20);
+        assert_eq!(received[0].symbol, "USDJPY");
+    }
+}
@@ -1,11 +1,21 @@
-use std::collections::VecDeque;
+use std::collections::{HashMap, VecDeque};
 
 use chrono::{DateTime, Utc};
-use log::error;
-use rust_decimal::{prelude::Zero, Decimal, MathematicalOps};
+use log::{error, info, trace};
+use rust_decimal::{prelude::ToPrimitive, Decimal, MathematicalOps};
 use serde::{Deserialize, Serialize};
 use serde_with::serde_as;
 
+use crate::{
+    consts::{DEFAULT_DUPLICATE_LOOKBACK, DEFAULT_TICKER_BUFFER_SIZE},
+    error::SignalError,
+    middleware::{
+        command::{Command, Side},
+        indicator::{IndicatorConfig, IndicatorSource, IndicatorValue, Indicators},
+        rolling::RollingStats,
+    },
+};
+
 #[serde_as]
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct Ticker {
@@ -15,6 +25,9 @@ pub struct Ticker {
     // optional fields
     pub flag: Option<u8>,
     pub side: Option<u8>,
+    // Originの注文指示、flagより優先する
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub command: Option<Command>,
     pub server_at: Option<DateTime<Utc>>,
     pub recived_at: Option<DateTime<Utc>>,
     pub diff_micros: Option<i64>,
@@ -26,10 +39,28 @@ impl Ticker {
         self.flag.unwrap_or(0)
     }
 
+    // 売買方向: 1 = 買い、2 = 売り、それ以外はなし
+    pub fn side(&self) -> Option<Side> {
+        match self.side {
+            Some(1) => Some(Side::Buy),
+            Some(2) => Some(Side::Sell),
+            _ => None,
+        }
+    }
+
     pub fn mid(&self) -> Decimal {
         (self.bid + self.ask) / Decimal::TWO
     }
 
+    pub fn spread(&self) -> Decimal {
+        self.ask - self.bid
+    }
+
+    // 集計に使用する時刻: 受信時刻を優先し、なければサーバー時刻
+    pub fn timestamp(&self) -> Option<DateTime<Utc>> {
+        self.recived_at.or(self.server_at)
+    }
+
     // CORE: サーバー時刻と受信時刻の差分を計算する
     pub fn culc_diff_micros(&mut self) {
         self.recived_at = Some(Utc::now());
@@ -40,17 +71,66 @@ impl Ticker {
     }
 }
 
+/// 統計対象の価格
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PriceField {
+    Bid,
+    Ask,
+    Mid,
+    Spread,
+}
+
+impl PriceField {
+    pub fn of(&self, t: &Ticker) -> Decimal {
+        match self {
+            PriceField::Bid => t.bid,
+            PriceField::Ask => t.ask,
+            PriceField::Mid => t.mid(),
+            PriceField::Spread => t.spread(),
+        }
+    }
+}
+
+impl std::str::FromStr for PriceField {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s {
+            "bid" => Ok(PriceField::Bid),
+            "ask" => Ok(PriceField::Ask),
+            "mid" => Ok(PriceField::Mid),
+            "spread" => Ok(PriceField::Spread),
+            _ => Err("無効なフィールド名です。".to_string()),
+        }
+    }
+}
+
 #[serde_as]
 #[derive(Debug, Clone, Deserialize, Serialize, Default)]
 pub struct TickerStats {
     data: VecDeque<Ticker>,
+    #[serde(default)]
+    bars: Vec<BarSeries>,
+    // push/shrink時に逐次更新する統計量
+    #[serde(default)]
+    bid_stats: RollingStats,
+    #[serde(default)]
+    ask_stats: RollingStats,
+    #[serde(default)]
+    mid_stats: RollingStats,
+    #[serde(default)]
+    spread_stats: RollingStats,
+    #[serde(skip)]
+    indicators: Indicators,
 }
 
 impl From<Vec<Ticker>> for TickerStats {
     fn from(tickers: Vec<Ticker>) -> Self {
-        TickerStats {
-            data: VecDeque::from(tickers),
+        let mut stats = TickerStats::new();
+        for t in tickers {
+            stats.push(t);
         }
+        stats
     }
 }
 
@@ -58,17 +138,182 @@ impl TickerStats {
     pub fn new() -> Self {
         TickerStats {
             data: VecDeque::new(),
+            ..Default::default()
         }
     }
 
     pub fn push(&mut self, t: Ticker) {
+        for series in self.bars.iter_mut() {
+            if let Some(bar) = series.push(&t) {
+                self.indicators.on_bar(series.interval(), &bar);
+            }
+        }
+        self.indicators.on_tick(&t);
+        self.bid_stats.push(t.bid);
+        self.ask_stats.push(t.ask);
+        self.mid_stats.push(t.mid());
+        self.spread_stats.push(t.spread());
         self.data.push_back(t);
     }
 
+    // CORE: 重複・順序を検査してから追加する
+    // 時刻のないtickは受信時刻を付与する
+    pub fn push_checked(&mut self, mut t: Ticker, policy: SequencePolicy) -> PushOutcome {
+        if t.timestamp().is_none() {
+            t.recived_at = Some(Utc::now());
+        }
+        if self.is_duplicate(&t) {
+            trace!("duplicate tick: {:?}", t);
+            return PushOutcome::Duplicate;
+        }
+
+        let at = t.timestamp();
+        let is_late = self
+            .last()
+            .and_then(|last| last.timestamp())
+            .is_some_and(|last| at < Some(last));
+        if !is_late {
+            self.push(t);
+            return PushOutcome::Pushed;
+        }
+
+        match policy {
+            SequencePolicy::Drop => {
+                trace!("out of order tick: {:?}", t);
+                PushOutcome::OutOfOrder
+            }
+            SequencePolicy::Resequence => {
+                self.insert_sorted(t);
+                PushOutcome::Resequenced
+            }
+        }
+    }
+
+    // 直近の同一server_at・同一価格のtickを重複とみなす
+    fn is_duplicate(&self, t: &Ticker) -> bool {
+        let Some(server_at) = t.server_at else {
+            return false;
+        };
+        self.data
+            .iter()
+            .rev()
+            .take(DEFAULT_DUPLICATE_LOOKBACK)
+            .any(|x| x.server_at == Some(server_at) && x.bid == t.bid && x.ask == t.ask)
+    }
+
+    // 遅延tickを時刻順の位置に挿入する
+    // 足・指標は逐次更新のため遅延tickを反映しない
+    fn insert_sorted(&mut self, t: Ticker) {
+        let at = t.timestamp();
+        let index = self.data.partition_point(|x| x.timestamp() <= at);
+        self.data.insert(index, t);
+
+        // 挿入位置が末尾でないため統計量を作り直す
+        self.bid_stats = RollingStats::new();
+        self.ask_stats = RollingStats::new();
+        self.mid_stats = RollingStats::new();
+        self.spread_stats = RollingStats::new();
+        for t in self.data.iter() {
+            self.bid_stats.push(t.bid);
+            self.ask_stats.push(t.ask);
+            self.mid_stats.push(t.mid());
+            self.spread_stats.push(t.spread());
+        }
+    }
+
+    // 最古のtickを取り除き、統計量からも差し引く
+    fn pop_front(&mut self) -> Option<Ticker> {
+        let t = self.data.pop_front()?;
+        self.bid_stats.pop(t.bid);
+        self.ask_stats.pop(t.ask);
+        self.mid_stats.pop(t.mid());
+        self.spread_stats.pop(t.spread());
+        Some(t)
+    }
+
+    // ウィンドウ内の統計量 (count/mean/variance/min/max)
+    pub fn rolling(&self, field: PriceField) -> &RollingStats {
+        match field {
+            PriceField::Bid => &self.bid_stats,
+            PriceField::Ask => &self.ask_stats,
+            PriceField::Mid => &self.mid_stats,
+            PriceField::Spread => &self.spread_stats,
+        }
+    }
+
+    // 最新のスプレッド
+    pub fn spread(&self) -> Option<Decimal> {
+        self.last().map(|t| t.spread())
+    }
+
+    // ウィンドウ内のスプレッド平均
+    pub fn spread_mean(&self) -> Option<Decimal> {
+        self.spread_stats.mean()
+    }
+
+    // ウィンドウ内のスプレッドのパーセンタイル (0-100, nearest-rank)
+    pub fn spread_percentile(&self, percentile: Decimal) -> Option<Decimal> {
+        if self.data.is_empty() || percentile < Decimal::ZERO || percentile > Decimal::ONE_HUNDRED {
+            return None;
+        }
+        let mut spreads: Vec<Decimal> = self.data.iter().map(|t| t.spread()).collect();
+        spreads.sort();
+        let rank = (percentile / Decimal::ONE_HUNDRED * Decimal::from(spreads.len()))
+            .ceil()
+            .to_usize()
+            .unwrap_or(1)
+            .max(1);
+        spreads.get(rank - 1).copied()
+    }
+
+    // 最新値のZスコア、データ不足または標準偏差0の場合はNone
+    pub fn zscore_last(&self, field: PriceField) -> Option<Decimal> {
+        let last = self.last()?;
+        self.rolling(field).zscore(field.of(last))
+    }
+
+    // 足の集計を追加する、同一intervalが既にあれば保持数のみ更新する
+    pub fn add_bars(&mut self, interval: BarInterval, limit_length: usize) {
+        match self.bars.iter_mut().find(|b| b.interval() == interval) {
+            Some(series) => series.set_limit(limit_length),
+            None => self.bars.push(BarSeries::new(interval, limit_length)),
+        }
+    }
+
+    pub fn bars(&self, interval: BarInterval) -> Option<&BarSeries> {
+        self.bars.iter().find(|b| b.interval() == interval)
+    }
+
+    // 指標を追加する、足を更新元とする場合は未登録なら足の集計も追加する
+    pub fn add_indicator(&mut self, config: IndicatorConfig) -> Result<(), SignalError> {
+        // 不正な設定は足を登録する前に拒否する
+        Indicators::new().add(config.clone())?;
+        if let IndicatorSource::Bar(interval) = config.source {
+            if self.bars(interval).is_none() {
+                self.add_bars(interval, DEFAULT_TICKER_BUFFER_SIZE);
+            }
+        }
+        self.indicators.add(config)
+    }
+
+    // 指標値を取得する、必要な入力数に達するまではNone
+    pub fn indicator(&self, name: &str) -> Option<IndicatorValue> {
+        self.indicators.get(name)
+    }
+
+    pub fn indicators(&self) -> &Indicators {
+        &self.indicators
+    }
+
     pub fn last(&self) -> Option<&Ticker> {
         self.data.back()
     }
 
+    // 古い順
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &Ticker> + ExactSizeIterator {
+        self.data.iter()
+    }
+
     pub fn len(&self) -> usize {
         self.data.len()
     }
@@ -84,67 +329,511 @@ impl TickerStats {
     }
 
     pub fn zscore(&self, field: &str) -> Result<Decimal, String> {
-        self.data.calculate_zscore_last(field)
+        let field = field.parse::<PriceField>()?;
+        if self.len() < 2 {
+            error!("データ数が2つ未満です。Zスコアを計算できません。");
+        }
+        Ok(self.zscore_last(field).unwrap_or(Decimal::ZERO))
+    }
+
+    // 最古から最新までの時刻の幅 (micros)
+    pub fn covered_micros(&self) -> i64 {
+        let first = self.data.iter().find_map(|t| t.timestamp());
+        let last = self.data.iter().rev().find_map(|t| t.timestamp());
+        match (first, last) {
+            (Some(first), Some(last)) => last
+                .signed_duration_since(first)
+                .num_microseconds()
+                .unwrap_or(i64::MAX),
+            _ => 0,
+        }
     }
 
     // 現在より指定micros以上前のデータを取得する
     // 配列で最新かつ指定micros以上前のデータを取得する
     // 現在値との差分を計算するために使用する
-    pub fn filter_micros(&self, micros: i64) -> Option<&Ticker> {
-        let latest = self.last().and_then(|f| f.recived_at.as_ref()).unwrap();
-
-        // Timestamps are monotonic, so we can binary search.
-        // We want the last element whose recived_at is more than `micros` before `latest`.
-        // Use partition_point on slices obtained from the VecDeque.
-        let (front, back) = self.data.as_slices();
-
-        // Helper: check if a ticker's timestamp is within the micros threshold
-        // We want the partition point where diff > micros transitions to diff <= micros
-        // Since timestamps are monotonic (oldest first), diffs decrease as we go forward.
-        // partition_point finds first element where predicate is false.
-        // Predicate: diff > micros (i.e., element is old enough)
-        let pred = |t: &Ticker| -> bool {
-            let diff = latest.signed_duration_since(t.recived_at.unwrap());
-            diff.num_microseconds().unwrap() > micros
+    // 遡れる履歴が不足している場合はInsufficientHistoryを返す
+    pub fn filter_micros(&self, micros: i64) -> Result<&Ticker, SignalError> {
+        let insufficient = || SignalError::InsufficientHistory {
+            required_micros: micros,
+            covered_micros: self.covered_micros(),
         };
+        let latest = self
+            .last()
+            .and_then(|t| t.timestamp())
+            .ok_or_else(insufficient)?;
 
-        // Search back slice first (it contains later elements)
-        let back_point = back.partition_point(|t| pred(t));
-        if back_point > 0 {
-            return Some(&back[back_point - 1]);
-        }
-
-        // Then search front slice
-        let front_point = front.partition_point(|t| pred(t));
-        if front_point > 0 {
-            return Some(&front[front_point - 1]);
-        }
+        let age = |t: &Ticker| {
+            t.timestamp().map(|at| {
+                latest
+                    .signed_duration_since(at)
+                    .num_microseconds()
+                    .unwrap_or(i64::MAX)
+            })
+        };
 
-        None
+        // 時刻のないtickやpushで追加した順不同のtickが混在しうるため二分探索はせず、
+        // 最新から遡って最初に指定micros以上前となるtickを返す
+        // 遡るのは指定期間内のtick数分のみ
+        self.data
+            .iter()
+            .rev()
+            .find(|t| age(t).is_some_and(|age| age > micros))
+            .ok_or_else(insufficient)
     }
 
     // CORE: 指定配列と現在の価格との差分を計算する
     // ターゲットとなる価格と現在の価格を比較して差分を計算する
     // ターゲットとなる価格は指定micros以上前の価格とし、filter_microsで取得する
-    pub fn diff(&self, micros: i64) -> Decimal {
-        let target = self.filter_micros(micros);
-        if let Some(t) = target {
-            let mid = self.mid();
-            let target_mid = t.mid();
-            mid - target_mid
-        } else {
-            Decimal::zero()
-        }
+    pub fn diff(&self, micros: i64) -> Result<Decimal, SignalError> {
+        let target = self.filter_micros(micros)?;
+        Ok(self.mid() - target.mid())
     }
 
     // 指定配列数に縮小する
     pub fn shrink(&mut self, limit_length: usize) {
         while self.data.len() > limit_length {
-            self.data.pop_front();
+            self.pop_front();
+        }
+    }
+
+    // 保持期間に従い縮小する
+    // min_micros以上遡れる状態を保ちつつ古いtickを捨て、max_lenを超える分は期間に関わらず捨てる
+    // min_microsが0以下の場合は保持数のみで縮小する
+    pub fn retain(&mut self, retention: &Retention) {
+        self.shrink(retention.max_len);
+        if retention.min_micros <= 0 {
+            return;
+        }
+
+        let Some(latest) = self.last().and_then(|t| t.timestamp()) else {
+            return;
+        };
+        // 2番目に古いtickだけでmin_microsを満たせるなら最古のtickは不要
+        while let Some(second) = self.data.get(1).and_then(|t| t.timestamp()) {
+            let age = latest
+                .signed_duration_since(second)
+                .num_microseconds()
+                .unwrap_or(i64::MAX);
+            if age <= retention.min_micros {
+                break;
+            }
+            self.pop_front();
         }
     }
 }
 
+/// スプレッド拡大時にエントリーを止める条件
+/// max: 固定の上限、ratio: 直近平均に対する倍率、いずれもNoneで無効
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub struct SpreadGate {
+    pub max: Option<Decimal>,
+    pub ratio: Option<Decimal>,
+}
+
+impl SpreadGate {
+    // 最新のスプレッドが条件を超えていればSpreadTooWideを返す
+    pub fn check(&self, tickers: &TickerStats) -> Result<(), SignalError> {
+        let Some(spread) = tickers.spread() else {
+            return Ok(());
+        };
+        if let Some(max) = self.max {
+            if spread > max {
+                return Err(SignalError::SpreadTooWide { spread, limit: max });
+            }
+        }
+        if let (Some(ratio), Some(mean)) = (self.ratio, tickers.spread_mean()) {
+            let limit = mean * ratio;
+            if spread > limit {
+                return Err(SignalError::SpreadTooWide { spread, limit });
+            }
+        }
+        Ok(())
+    }
+
+    // 新規注文の判定に使用する、スプレッド拡大中は理由をログに残してfalseを返す
+    pub fn allows_entry(&self, tickers: &TickerStats) -> bool {
+        match self.check(tickers) {
+            Ok(()) => true,
+            Err(e) => {
+                info!("skip entry: {}", e);
+                false
+            }
+        }
+    }
+}
+
+/// 遅延tickの扱い
+/// Drop: 破棄する、Resequence: 時刻順の位置に挿入する
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum SequencePolicy {
+    #[default]
+    Drop,
+    Resequence,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum PushOutcome {
+    Pushed,
+    Resequenced,
+    Duplicate,
+    OutOfOrder,
+}
+
+/// TickerStatsの保持方針
+/// min_micros: 最低限遡れるようにする期間、max_len: 期間に関わらない保持数の上限
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Retention {
+    pub min_micros: i64,
+    pub max_len: usize,
+}
+
+impl Retention {
+    pub fn new(min_micros: i64, max_len: usize) -> Self {
+        Retention { min_micros, max_len }
+    }
+
+    // 保持数のみで縮小する
+    pub fn count(max_len: usize) -> Self {
+        Retention {
+            min_micros: 0,
+            max_len,
+        }
+    }
+
+    // 両方の保持方針を満たす（期間・保持数とも長い方）
+    pub fn cover(&self, other: &Retention) -> Retention {
+        Retention {
+            min_micros: self.min_micros.max(other.min_micros),
+            max_len: self.max_len.max(other.max_len),
+        }
+    }
+}
+
+/// 足の区切り
+/// Time: 指定ミリ秒毎の時間足、Ticks: 指定tick数毎のtick足
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Deserialize, Serialize)]
+pub enum BarInterval {
+    Time(i64),
+    Ticks(u32),
+}
+
+impl BarInterval {
+    pub const S1: BarInterval = BarInterval::Time(1_000);
+    pub const S5: BarInterval = BarInterval::Time(5_000);
+    pub const M1: BarInterval = BarInterval::Time(60_000);
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub struct Ohlc {
+    pub open: Decimal,
+    pub high: Decimal,
+    pub low: Decimal,
+    pub close: Decimal,
+}
+
+impl Ohlc {
+    pub fn new(v: Decimal) -> Self {
+        Ohlc {
+            open: v,
+            high: v,
+            low: v,
+            close: v,
+        }
+    }
+
+    pub fn update(&mut self, v: Decimal) {
+        self.high = self.high.max(v);
+        self.low = self.low.min(v);
+        self.close = v;
+    }
+}
+
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct Bar {
+    pub symbol: String,
+    pub bid: Ohlc,
+    pub ask: Ohlc,
+    pub mid: Ohlc,
+    pub spread: Ohlc,
+    pub ticks: u32,
+    pub opened_at: DateTime<Utc>,
+    pub updated_at: DateTime<Utc>,
+}
+
+impl Bar {
+    pub fn new(t: &Ticker, at: DateTime<Utc>) -> Self {
+        Bar {
+            symbol: t.symbol.clone(),
+            bid: Ohlc::new(t.bid),
+            ask: Ohlc::new(t.ask),
+            mid: Ohlc::new(t.mid()),
+            spread: Ohlc::new(t.spread()),
+            ticks: 1,
+            opened_at: at,
+            updated_at: at,
+        }
+    }
+
+    pub fn update(&mut self, t: &Ticker, at: DateTime<Utc>) {
+        self.bid.update(t.bid);
+        self.ask.update(t.ask);
+        self.mid.update(t.mid());
+        self.spread.update(t.spread());
+        self.ticks += 1;
+        self.updated_at = at;
+    }
+}
+
+/// Tickerから逐次的に足を生成し、確定足を指定数保持する
+/// 時間足は次の区間のtickを受信した時点で確定する（空の足は生成しない）
+#[derive(Debug, Clone, Deserialize, Serialize)]
+pub struct BarSeries {
+    interval: BarInterval,
+    limit: usize,
+    current: Option<Bar>,
+    bucket: i64,
+    bars: VecDeque<Bar>,
+}
+
+impl BarSeries {
+    pub fn new(interval: BarInterval, limit_length: usize) -> Self {
+        BarSeries {
+            interval,
+            limit: limit_length,
+            current: None,
+            bucket: 0,
+            bars: VecDeque::new(),
+        }
+    }
+
+    pub fn interval(&self) -> BarInterval {
+        self.interval
+    }
+
+    pub fn set_limit(&mut self, limit_length: usize) {
+        self.limit = limit_length;
+        self.shrink();
+    }
+
+    // CORE: tickを集計し、足が確定した場合は確定足を返す
+    pub fn push(&mut self, t: &Ticker) -> Option<Bar> {
+        let at = t.timestamp().unwrap_or_else(Utc::now);
+
+        match self.interval {
+            BarInterval::Time(ms) => {
+                let bucket = at.timestamp_millis().div_euclid(ms.max(1));
+                let closed = match self.current.as_mut() {
+                    Some(bar) if self.bucket == bucket => {
+                        bar.update(t, at);
+                        None
+                    }
+                    _ => self.current.replace(Bar::new(t, at)),
+                };
+                self.bucket = bucket;
+                closed.map(|bar| self.close(bar))
+            }
+            BarInterval::Ticks(n) => {
+                match self.current.as_mut() {
+                    Some(bar) => bar.update(t, at),
+                    None => self.current = Some(Bar::new(t, at)),
+                }
+                let is_full = self.current.as_ref().is_some_and(|bar| bar.ticks >= n);
+                if is_full {
+                    self.current.take().map(|bar| self.close(bar))
+                } else {
+                    None
+                }
+            }
+        }
+    }
+
+    fn close(&mut self, bar: Bar) -> Bar {
+        self.bars.push_back(bar.clone());
+        self.shrink();
+        bar
+    }
+
+    fn shrink(&mut self) {
+        while self.bars.len() > self.limit {
+            self.bars.pop_front();
+        }
+    }
+
+    // 集計中の未確定足
+    pub fn current(&self) -> Option<&Bar> {
+        self.current.as_ref()
+    }
+
+    // 直近の確定足
+    pub fn last(&self) -> Option<&Bar> {
+        self.bars.back()
+    }
+
+    pub fn bars(&self) -> &VecDeque<Bar> {
+        &self.bars
+    }
+
+    pub fn len(&self) -> usize {
+        self.bars.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.bars.is_empty()
+    }
+}
+
+/// シンボル毎にTickerStatsを保持する
+/// 同一ソケットに複数銘柄が流れる場合でも、銘柄を跨いだdiffを計算しない
+#[derive(Debug, Clone)]
+pub struct SymbolBook {
+    books: HashMap<String, TickerStats>,
+    retentions: HashMap<String, Retention>,
+    default_retention: Retention,
+    bar_intervals: Vec<(BarInterval, usize)>,
+    indicators: Vec<IndicatorConfig>,
+    symbol_indicators: HashMap<String, Vec<IndicatorConfig>>,
+}
+
+impl Default for SymbolBook {
+    fn default() -> Self {
+        SymbolBook::new(DEFAULT_TICKER_BUFFER_SIZE)
+    }
+}
+
+impl SymbolBook {
+    pub fn new(default_limit: usize) -> Self {
+        SymbolBook::with_retention(Retention::count(default_limit))
+    }
+
+    pub fn with_retention(default_retention: Retention) -> Self {
+        SymbolBook {
+            books: HashMap::new(),
+            retentions: HashMap::new(),
+            default_retention,
+            bar_intervals: Vec::new(),
+            indicators: Vec::new(),
+            symbol_indicators: HashMap::new(),
+        }
+    }
+
+    // 全シンボルに足の集計を追加する（以降に登録されるシンボルにも適用される）
+    pub fn add_bars(&mut self, interval: BarInterval, limit_length: usize) {
+        self.bar_intervals.retain(|(i, _)| *i != interval);
+        self.bar_intervals.push((interval, limit_length));
+        for stats in self.books.values_mut() {
+            stats.add_bars(interval, limit_length);
+        }
+    }
+
+    // 登録済みの足の集計 (足の区切り, 保持数)
+    pub fn bar_intervals(&self) -> &[(BarInterval, usize)] {
+        &self.bar_intervals
+    }
+
+    // 全シンボルに指標を追加する（以降に登録されるシンボルにも適用される）
+    // 全シンボルに反映する前に設定を検証し、一部のシンボルのみ追加された状態にしない
+    pub fn add_indicator(&mut self, config: IndicatorConfig) -> Result<(), SignalError> {
+        Indicators::new().add(config.clone())?;
+        for stats in self.books.values_mut() {
+            stats.add_indicator(config.clone())?;
+        }
+        self.indicators.retain(|c| c.name != config.name);
+        self.indicators.push(config);
+        Ok(())
+    }
+
+    // シンボル個別に指標を追加する
+    pub fn add_symbol_indicator(&mut self, symbol: &str, config: IndicatorConfig) -> Result<(), SignalError> {
+        Indicators::new().add(config.clone())?;
+        if let Some(stats) = self.books.get_mut(symbol) {
+            stats.add_indicator(config.clone())?;
+        }
+        let configs = self.symbol_indicators.entry(symbol.to_string()).or_default();
+        configs.retain(|c| c.name != config.name);
+        configs.push(config);
+        Ok(())
+    }
+
+    // シンボル個別の保持数を設定する
+    pub fn set_limit(&mut self, symbol: &str, limit_length: usize) {
+        self.set_retention(symbol, Retention::count(limit_length));
+    }
+
+    // シンボル個別の保持方針を設定する
+    pub fn set_retention(&mut self, symbol: &str, retention: Retention) {
+        self.retentions.insert(symbol.to_string(), retention);
+        if let Some(stats) = self.books.get_mut(symbol) {
+            stats.retain(&retention);
+        }
+    }
+
+    pub fn retention(&self, symbol: &str) -> Retention {
+        self.retentions
+            .get(symbol)
+            .copied()
+            .unwrap_or(self.default_retention)
+    }
+
+    pub fn limit(&self, symbol: &str) -> usize {
+        self.retention(symbol).max_len
+    }
+
+    // シンボルのTickerStatsを取得する、未登録なら足・指標の設定を適用して生成する
+    fn stats_mut(&mut self, symbol: &str) -> &mut TickerStats {
+        let bar_intervals = &self.bar_intervals;
+        let indicators = self
+            .indicators
+            .iter()
+            .chain(self.symbol_indicators.get(symbol).into_iter().flatten());
+        self.books.entry(symbol.to_string()).or_insert_with(|| {
+            let mut stats = TickerStats::new();
+            for (interval, limit) in bar_intervals {
+                stats.add_bars(*interval, *limit);
+            }
+            // 登録時に検証済みのため失敗しない
+            for config in indicators {
+                let _ = stats.add_indicator(config.clone());
+            }
+            stats
+        })
+    }
+
+    // CORE: tickerをシンボルに対応するTickerStatsへ振り分け、保持方針に従い縮小する
+    pub fn push(&mut self, t: Ticker) -> &TickerStats {
+        let retention = self.retention(&t.symbol);
+        let stats = self.stats_mut(&t.symbol);
+        stats.push(t);
+        stats.retain(&retention);
+        stats
+    }
+
+    // 重複・順序を検査してから振り分ける
+    pub fn push_checked(&mut self, t: Ticker, policy: SequencePolicy) -> PushOutcome {
+        let retention = self.retention(&t.symbol);
+        let stats = self.stats_mut(&t.symbol);
+        let outcome = stats.push_checked(t, policy);
+        stats.retain(&retention);
+        outcome
+    }
+
+    pub fn get(&self, symbol: &str) -> Option<&TickerStats> {
+        self.books.get(symbol)
+    }
+
+    pub fn symbols(&self) -> impl Iterator<Item = &String> {
+        self.books.keys()
+    }
+
+    pub fn len(&self) -> usize {
+        self.books.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.books.is_empty()
+    }
+}
+
 // Vec<Ticker> / VecDeque<Ticker>への汎用的な処理
 pub trait Tickers {
     #[allow(unused)]
@@ -236,6 +925,7 @@ mod tests {
                 ask: Decimal::from_f32(110.1).unwrap(),
                 flag: Some(0),
                 side: Some(0),
+                command: None,
                 server_at: Some(Utc::now()),
                 recived_at: Some(Utc::now()),
                 diff_micros: Some(0),
@@ -246,6 +936,7 @@ mod tests {
                 ask: Decimal::from_f32(110.2).unwrap(),
                 flag: Some(0),
                 side: Some(0),
+                command: None,
                 server_at: Some(Utc::now()),
                 recived_at: Some(Utc::now()),
                 diff_micros: Some(0),
@@ -285,6 +976,7 @@ mod tests {
                 ask: Decimal::from_str(record.get(2).unwrap()).unwrap(),
                 flag: Some(0),
                 side: Some(0),
+                command: None,
                 server_at: Some(server_at),
                 recived_at: Some(server_at),
                 diff_micros: Some(0),
@@ -394,7 +1086,10 @@ mod tests {
         ]);
         // Only 1 ticker, filter_micros should find nothing
         let diff = ts.diff(1_000_000); // 1 second ago
-        assert_eq!(diff, Decimal::ZERO);
+        assert!(matches!(
+            diff,
+            Err(SignalError::InsufficientHistory { required_micros: 1_000_000, covered_micros: 0 })
+        ));
     }
 
     #[test]
@@ -417,7 +1112,7 @@ mod tests {
         ]);
         // Looking for ticker > 100ms ago - should find the first one
         let found = ts.filter_micros(100_000); // 100ms in micros
-        assert!(found.is_some());
+        assert!(found.is_ok());
         assert_eq!(found.unwrap().bid, Decimal::from_str("100.0").unwrap());
     }
 
@@ -439,7 +1134,7 @@ mod tests {
                 ..Default::default()
             },
         ]);
-        let diff = ts.diff(100_000); // 100ms
+        let diff = ts.diff(100_000).unwrap(); // 100ms
         assert_eq!(diff, Decimal::from_str("1.0").unwrap()); // 101 - 100 = 1
     }
 
@@ -457,6 +1152,467 @@ mod tests {
         assert_eq!(result, Decimal::ZERO);
     }
 
+    #[test]
+    fn test_symbol_book_separates_symbols() {
+        let mut book = SymbolBook::new(10);
+        book.push(Ticker {
+            symbol: "USDJPY".to_string(),
+            bid: Decimal::from_str("150.0").unwrap(),
+            ask: Decimal::from_str("150.0").unwrap(),
+            ..Default::default()
+        });
+        book.push(Ticker {
+            symbol: "EURUSD".to_string(),
+            bid: Decimal::from_str("1.08").unwrap(),
+            ask: Decimal::from_str("1.08").unwrap(),
+            ..Default::default()
+        });
+        let stats = book.push(Ticker {
+            symbol: "USDJPY".to_string(),
+            bid: Decimal::from_str("150.2").unwrap(),
+            ask: Decimal::from_str("150.2").unwrap(),
+            ..Default::default()
+        });
+        assert_eq!(stats.len(), 2);
+        assert_eq!(stats.mid(), Decimal::from_str("150.2").unwrap());
+
+        assert_eq!(book.len(), 2);
+        assert_eq!(book.get("EURUSD").unwrap().len(), 1);
+        assert!(book.get("GBPUSD").is_none());
+    }
+
+    #[test]
+    fn test_symbol_book_limit_per_symbol() {
+        let mut book = SymbolBook::new(5);
+        book.set_limit("EURUSD", 2);
+        for _ in 0..10 {
+            book.push(Ticker { symbol: "USDJPY".to_string(), ..Default::default() });
+            book.push(Ticker { symbol: "EURUSD".to_string(), ..Default::default() });
+        }
+        assert_eq!(book.limit("USDJPY"), 5);
+        assert_eq!(book.get("USDJPY").unwrap().len(), 5);
+        assert_eq!(book.get("EURUSD").unwrap().len(), 2);
+
+        // 既存データも新しい保持数に縮小される
+        book.set_limit("USDJPY", 3);
+        assert_eq!(book.get("USDJPY").unwrap().len(), 3);
+    }
+
+    #[test]
+    fn test_symbol_book_default_limit() {
+        let book = SymbolBook::default();
+        assert!(book.is_empty());
+        assert_eq!(book.limit("USDJPY"), DEFAULT_TICKER_BUFFER_SIZE);
+    }
+
+    fn bar_ticker(bid: &str, ask: &str, at: DateTime<Utc>) -> Ticker {
+        Ticker {
+            symbol: "USDJPY".to_string(),
+            bid: Decimal::from_str(bid).unwrap(),
+            ask: Decimal::from_str(ask).unwrap(),
+            recived_at: Some(at),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_bar_series_time_bars() {
+        use chrono::TimeZone;
+        let base = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+        let ms = |n: i64| base + chrono::Duration::milliseconds(n);
+
+        let mut series = BarSeries::new(BarInterval::S1, 10);
+        assert!(series.push(&bar_ticker("150.00", "150.02", ms(0))).is_none());
+        assert!(series.push(&bar_ticker("150.05", "150.06", ms(300))).is_none());
+        assert!(series.push(&bar_ticker("149.98", "150.01", ms(900))).is_none());
+
+        // 次の区間のtickで確定する
+        let bar = series.push(&bar_ticker("150.10", "150.12", ms(1_200))).unwrap();
+        assert_eq!(bar.ticks, 3);
+        assert_eq!(bar.bid.open, Decimal::from_str("150.00").unwrap());
+        assert_eq!(bar.bid.high, Decimal::from_str("150.05").unwrap());
+        assert_eq!(bar.bid.low, Decimal::from_str("149.98").unwrap());
+        assert_eq!(bar.bid.close, Decimal::from_str("149.98").unwrap());
+        assert_eq!(bar.ask.high, Decimal::from_str("150.06").unwrap());
+        assert_eq!(bar.mid.close, Decimal::from_str("149.995").unwrap());
+        assert_eq!(bar.spread.low, Decimal::from_str("0.01").unwrap());
+        assert_eq!(bar.spread.high, Decimal::from_str("0.03").unwrap());
+        assert_eq!(bar.opened_at, ms(0));
+        assert_eq!(bar.updated_at, ms(900));
+
+        assert_eq!(series.len(), 1);
+        assert_eq!(series.current().unwrap().ticks, 1);
+    }
+
+    #[test]
+    fn test_bar_series_tick_bars_and_limit() {
+        let now = Utc::now();
+        let mut series = BarSeries::new(BarInterval::Ticks(2), 3);
+        let mut closed = 0;
+        for i in 0..10 {
+            if series.push(&bar_ticker("100.0", "100.1", now)).is_some() {
+                closed += 1;
+            }
+            assert_eq!(series.current().is_none(), i % 2 == 1);
+        }
+        assert_eq!(closed, 5);
+        assert_eq!(series.len(), 3);
+        assert_eq!(series.last().unwrap().ticks, 2);
+    }
+
+    #[test]
+    fn test_ticker_stats_bars() {
+        use chrono::TimeZone;
+        let base = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+        let mut ts = TickerStats::new();
+        ts.add_bars(BarInterval::S1, 10);
+        ts.add_bars(BarInterval::Ticks(3), 10);
+        for i in 0..6 {
+            ts.push(bar_ticker("100.0", "100.1", base + chrono::Duration::milliseconds(i * 400)));
+        }
+        assert_eq!(ts.bars(BarInterval::Ticks(3)).unwrap().len(), 2);
+        // 0-999ms, 1000-1999ms が確定、2000ms以降は集計中
+        assert_eq!(ts.bars(BarInterval::S1).unwrap().len(), 2);
+        assert!(ts.bars(BarInterval::M1).is_none());
+    }
+
+    #[test]
+    fn test_symbol_book_bars_for_new_symbols() {
+        let mut book = SymbolBook::default();
+        book.add_bars(BarInterval::Ticks(2), 5);
+        book.push(Ticker { symbol: "USDJPY".to_string(), ..Default::default() });
+        book.push(Ticker { symbol: "USDJPY".to_string(), ..Default::default() });
+        book.push(Ticker { symbol: "EURUSD".to_string(), ..Default::default() });
+
+        let usdjpy = book.get("USDJPY").unwrap().bars(BarInterval::Ticks(2)).unwrap();
+        assert_eq!(usdjpy.len(), 1);
+        let eurusd = book.get("EURUSD").unwrap().bars(BarInterval::Ticks(2)).unwrap();
+        assert!(eurusd.is_empty());
+    }
+
+    #[test]
+    fn test_ticker_stats_rolling_follows_shrink() {
+        let mut ts = TickerStats::new();
+        for i in 0..10 {
+            let price = Decimal::from(100 + i);
+            ts.push(Ticker { bid: price, ask: price + Decimal::ONE, ..Default::default() });
+            ts.shrink(4);
+        }
+        // 106..=109 が残る
+        let bid = ts.rolling(PriceField::Bid);
+        assert_eq!(bid.count(), 4);
+        assert_eq!(bid.min().unwrap(), Decimal::from(106));
+        assert_eq!(bid.max().unwrap(), Decimal::from(109));
+        assert_eq!(bid.mean().unwrap(), Decimal::from_str("107.5").unwrap());
+        assert_eq!(ts.rolling(PriceField::Ask).max().unwrap(), Decimal::from(110));
+        assert_eq!(ts.rolling(PriceField::Mid).min().unwrap(), Decimal::from_str("106.5").unwrap());
+    }
+
+    #[test]
+    fn test_ticker_stats_zscore_matches_full_window() {
+        let mut ts = TickerStats::new();
+        let mut window = VecDeque::new();
+        for i in 0..30 {
+            let price = Decimal::from_str("150.000").unwrap() + Decimal::new((i * 7) % 11, 3);
+            let t = Ticker { bid: price, ask: price + Decimal::new(2, 3), ..Default::default() };
+            ts.push(t.clone());
+            ts.shrink(12);
+            window.push_back(t);
+            if window.len() > 12 {
+                window.pop_front();
+            }
+        }
+        let full = window.calculate_zscore_last("bid").unwrap();
+        let rolling = ts.zscore_last(PriceField::Bid).unwrap();
+        assert!((full - rolling).abs() < Decimal::from_str("0.0000001").unwrap());
+        assert_eq!(ts.zscore("bid").unwrap(), rolling);
+    }
+
+    #[test]
+    fn test_ticker_stats_indicator_from_bars() {
+        use crate::middleware::indicator::IndicatorKind;
+        let mut ts = TickerStats::new();
+        ts.add_indicator(IndicatorConfig::new(
+            "sma2",
+            IndicatorKind::Sma(2),
+            IndicatorSource::Bar(BarInterval::Ticks(2)),
+        ))
+        .unwrap();
+        // 足の集計が自動で追加される
+        assert!(ts.bars(BarInterval::Ticks(2)).is_some());
+
+        for p in [100, 102, 104, 106] {
+            ts.push(Ticker { bid: Decimal::from(p), ask: Decimal::from(p), ..Default::default() });
+        }
+        // 確定足のmid close: 102, 106
+        assert_eq!(ts.indicator("sma2").unwrap().value(), 104.0);
+        assert!(ts.indicator("unknown").is_none());
+    }
+
+    #[test]
+    fn test_symbol_book_indicators_per_symbol() {
+        use crate::middleware::indicator::IndicatorKind;
+        let mut book = SymbolBook::default();
+        book.add_indicator(IndicatorConfig::new("ema", IndicatorKind::Ema(2), IndicatorSource::Tick))
+            .unwrap();
+        book.add_symbol_indicator(
+            "USDJPY",
+            IndicatorConfig::new("rsi", IndicatorKind::Rsi(2), IndicatorSource::Tick),
+        )
+        .unwrap();
+        assert!(book
+            .add_indicator(IndicatorConfig::new("bad", IndicatorKind::Ema(0), IndicatorSource::Tick))
+            .is_err());
+
+        for symbol in ["USDJPY", "EURUSD", "USDJPY", "EURUSD"] {
+            book.push(Ticker { symbol: symbol.to_string(), ..Default::default() });
+        }
+        let usdjpy = book.get("USDJPY").unwrap();
+        assert!(usdjpy.indicator("ema").is_some());
+        assert!(usdjpy.indicator("rsi").is_some());
+        let eurusd = book.get("EURUSD").unwrap();
+        assert!(eurusd.indicator("ema").is_some());
+        assert_eq!(eurusd.indicators().len(), 1);
+
+        // 既存のシンボルにも、不正な設定では足・指標を追加しない
+        let bad = IndicatorConfig::new("bad", IndicatorKind::Ema(0), IndicatorSource::Bar(BarInterval::M1));
+        assert!(book.add_indicator(bad.clone()).is_err());
+        assert!(book.add_symbol_indicator("USDJPY", bad).is_err());
+        for symbol in ["USDJPY", "EURUSD"] {
+            let stats = book.get(symbol).unwrap();
+            assert!(stats.bars(BarInterval::M1).is_none());
+            assert!(stats.indicator("bad").is_none());
+        }
+    }
+
+    #[test]
+    fn test_ticker_stats_diff_empty() {
+        let ts = TickerStats::new();
+        assert!(matches!(ts.diff(100_000), Err(SignalError::InsufficientHistory { .. })));
+    }
+
+    #[test]
+    fn test_ticker_stats_diff_insufficient_coverage() {
+        use chrono::Duration;
+        let now = Utc::now();
+        let ts = TickerStats::from(vec![
+            Ticker { recived_at: Some(now - Duration::milliseconds(500)), ..Default::default() },
+            Ticker { recived_at: Some(now), ..Default::default() },
+        ]);
+        // 500msしか遡れないため3秒前は取得できない
+        match ts.filter_micros(3_000_000) {
+            Err(SignalError::InsufficientHistory { required_micros, covered_micros }) => {
+                assert_eq!(required_micros, 3_000_000);
+                assert_eq!(covered_micros, 500_000);
+            }
+            r => panic!("unexpected result: {:?}", r),
+        }
+    }
+
+    #[test]
+    fn test_ticker_stats_retain_keeps_duration() {
+        use chrono::Duration;
+        let now = Utc::now();
+        let mut ts = TickerStats::new();
+        // 1秒間隔で10tick
+        for i in 0..10 {
+            ts.push(Ticker { recived_at: Some(now + Duration::seconds(i)), ..Default::default() });
+        }
+        ts.retain(&Retention::new(3_000_000, 100));
+        // 最新(9s)から3秒超遡れる5s以降が残る
+        assert_eq!(ts.len(), 5);
+        assert!(ts.filter_micros(3_000_000).is_ok());
+        assert_eq!(ts.covered_micros(), 4_000_000);
+    }
+
+    #[test]
+    fn test_ticker_stats_retain_max_len_cap() {
+        use chrono::Duration;
+        let now = Utc::now();
+        let mut ts = TickerStats::new();
+        // 10ms間隔で500tick = 5秒分
+        for i in 0..500 {
+            ts.push(Ticker { recived_at: Some(now + Duration::milliseconds(i * 10)), ..Default::default() });
+        }
+        ts.retain(&Retention::new(3_000_000, 200));
+        assert_eq!(ts.len(), 200);
+        assert!(ts.filter_micros(3_000_000).is_err());
+    }
+
+    #[test]
+    fn test_symbol_book_retention() {
+        use chrono::Duration;
+        let now = Utc::now();
+        let mut book = SymbolBook::with_retention(Retention::new(1_000_000, 1_000));
+        book.set_limit("EURUSD", 3);
+        for i in 0..20 {
+            let at = Some(now + Duration::milliseconds(i * 200));
+            book.push(Ticker { symbol: "USDJPY".to_string(), recived_at: at, ..Default::default() });
+            book.push(Ticker { symbol: "EURUSD".to_string(), recived_at: at, ..Default::default() });
+        }
+        // 1秒超遡れる最小の7tick (2.6s..=3.8s)
+        assert_eq!(book.get("USDJPY").unwrap().len(), 7);
+        assert_eq!(book.get("EURUSD").unwrap().len(), 3);
+        assert_eq!(book.retention("EURUSD"), Retention::count(3));
+        assert_eq!(
+            Retention::count(3).cover(&Retention::new(1_000_000, 2)),
+            Retention::new(1_000_000, 3)
+        );
+    }
+
+    fn seq_ticker(bid: i64, server_ms: i64, recived_ms: i64) -> Ticker {
+        use chrono::TimeZone;
+        let base = Utc.timestamp_opt(1_700_000_000, 0).unwrap();
+        Ticker {
+            symbol: "USDJPY".to_string(),
+            bid: Decimal::from(bid),
+            ask: Decimal::from(bid),
+            server_at: Some(base + chrono::Duration::milliseconds(server_ms)),
+            recived_at: Some(base + chrono::Duration::milliseconds(recived_ms)),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_ticker_stats_push_checked_duplicate() {
+        let mut ts = TickerStats::new();
+        assert_eq!(ts.push_checked(seq_ticker(100, 0, 10), SequencePolicy::Drop), PushOutcome::Pushed);
+        // 同一server_at・同一価格は受信時刻が異なっても重複
+        assert_eq!(ts.push_checked(seq_ticker(100, 0, 20), SequencePolicy::Drop), PushOutcome::Duplicate);
+        // 価格が異なれば重複ではない
+        assert_eq!(ts.push_checked(seq_ticker(101, 0, 30), SequencePolicy::Drop), PushOutcome::Pushed);
+        assert_eq!(ts.len(), 2);
+    }
+
+    #[test]
+    fn test_ticker_stats_push_checked_out_of_order() {
+        let mut ts = TickerStats::new();
+        ts.push_checked(seq_ticker(100, 0, 0), SequencePolicy::Drop);
+        ts.push_checked(seq_ticker(102, 200, 200), SequencePolicy::Drop);
+
+        let late = seq_ticker(101, 100, 100);
+        assert_eq!(ts.push_checked(late.clone(), SequencePolicy::Drop), PushOutcome::OutOfOrder);
+        assert_eq!(ts.len(), 2);
+
+        assert_eq!(ts.push_checked(late, SequencePolicy::Resequence), PushOutcome::Resequenced);
+        assert_eq!(ts.len(), 3);
+        // 時刻順に並び、最新は変わらない
+        assert_eq!(ts.last().unwrap().bid, Decimal::from(102));
+        assert_eq!(ts.filter_micros(50_000).unwrap().bid, Decimal::from(101));
+        assert_eq!(ts.rolling(PriceField::Bid).min().unwrap(), Decimal::from(100));
+        assert_eq!(ts.rolling(PriceField::Bid).mean().unwrap(), Decimal::from(101));
+
+        // 縮小後も統計量が一致する
+        ts.shrink(1);
+        assert_eq!(ts.rolling(PriceField::Bid).min().unwrap(), Decimal::from(102));
+    }
+
+    #[test]
+    fn test_ticker_stats_push_checked_stamps_missing_timestamp() {
+        let mut ts = TickerStats::new();
+        assert_eq!(ts.push_checked(Ticker::default(), SequencePolicy::Drop), PushOutcome::Pushed);
+        assert!(ts.last().unwrap().recived_at.is_some());
+    }
+
+    #[test]
+    fn test_ticker_stats_filter_micros_missing_timestamps() {
+        use chrono::Duration;
+        let now = Utc::now();
+        let ts = TickerStats::from(vec![
+            Ticker { bid: Decimal::from(100), recived_at: Some(now - Duration::seconds(2)), ..Default::default() },
+            Ticker { bid: Decimal::from(101), ..Default::default() },
+            Ticker { bid: Decimal::from(102), ..Default::default() },
+            Ticker { bid: Decimal::from(103), recived_at: Some(now), ..Default::default() },
+        ]);
+        // 時刻のないtickを飛ばして時刻を持つtickを返す
+        assert_eq!(ts.filter_micros(1_000_000).unwrap().bid, Decimal::from(100));
+        assert_eq!(ts.covered_micros(), 2_000_000);
+
+        // 最新tickに時刻がなくてもpanicしない
+        let ts = TickerStats::from(vec![Ticker::default(), Ticker::default()]);
+        assert!(ts.filter_micros(1_000).is_err());
+        assert!(ts.diff(1_000).is_err());
+
+        // pushで順不同に追加されても、最新から遡って最初に条件を満たすtickを返す
+        let at = |ms: i64, bid: i64| Ticker {
+            bid: Decimal::from(bid),
+            recived_at: Some(now + Duration::milliseconds(ms)),
+            ..Default::default()
+        };
+        let ts = TickerStats::from(vec![at(-3_000, 100), at(-2_000, 101), at(-500, 102), at(-1_500, 103), at(0, 104)]);
+        assert_eq!(ts.filter_micros(1_000_000).unwrap().bid, Decimal::from(103));
+    }
+
+    #[test]
+    fn test_symbol_book_push_checked() {
+        let mut book = SymbolBook::default();
+        assert_eq!(book.push_checked(seq_ticker(100, 100, 100), SequencePolicy::Drop), PushOutcome::Pushed);
+        assert_eq!(book.push_checked(seq_ticker(100, 100, 100), SequencePolicy::Drop), PushOutcome::Duplicate);
+        assert_eq!(book.push_checked(seq_ticker(99, 0, 0), SequencePolicy::Drop), PushOutcome::OutOfOrder);
+        assert_eq!(book.get("USDJPY").unwrap().len(), 1);
+    }
+
+    fn spread_ticker(bid: &str, ask: &str) -> Ticker {
+        Ticker {
+            symbol: "USDJPY".to_string(),
+            bid: Decimal::from_str(bid).unwrap(),
+            ask: Decimal::from_str(ask).unwrap(),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn test_ticker_stats_spread_analytics() {
+        let mut ts = TickerStats::new();
+        for (bid, ask) in [("150.00", "150.01"), ("150.00", "150.02"), ("150.00", "150.03"), ("150.00", "150.10")] {
+            ts.push(spread_ticker(bid, ask));
+        }
+        assert_eq!(ts.spread(), Some(Decimal::from_str("0.10").unwrap()));
+        assert_eq!(ts.spread_mean(), Some(Decimal::from_str("0.04").unwrap()));
+        assert_eq!(ts.spread_percentile(Decimal::from(50)), Some(Decimal::from_str("0.02").unwrap()));
+        assert_eq!(ts.spread_percentile(Decimal::from(100)), Some(Decimal::from_str("0.10").unwrap()));
+        assert_eq!(ts.spread_percentile(Decimal::ZERO), Some(Decimal::from_str("0.01").unwrap()));
+        assert_eq!(ts.spread_percentile(Decimal::from(101)), None);
+        assert_eq!(ts.rolling(PriceField::Spread).max(), Some(Decimal::from_str("0.10").unwrap()));
+
+        // 縮小後は統計量からも除外される
+        ts.shrink(2);
+        assert_eq!(ts.rolling(PriceField::Spread).min(), Some(Decimal::from_str("0.03").unwrap()));
+        assert_eq!(TickerStats::new().spread_percentile(Decimal::from(50)), None);
+    }
+
+    #[test]
+    fn test_spread_gate() {
+        let mut ts = TickerStats::new();
+        for _ in 0..9 {
+            ts.push(spread_ticker("150.00", "150.01"));
+        }
+        // 無効な条件は常に通す
+        assert!(SpreadGate::default().check(&ts).is_ok());
+        assert!(SpreadGate::default().check(&TickerStats::new()).is_ok());
+
+        let fixed = SpreadGate {
+            max: Some(Decimal::from_str("0.05").unwrap()),
+            ratio: None,
+        };
+        let ratio = SpreadGate {
+            max: None,
+            ratio: Some(Decimal::from(3)),
+        };
+        assert!(fixed.check(&ts).is_ok());
+        assert!(ratio.check(&ts).is_ok());
+
+        // 平均0.014に対して0.05は3倍超、固定上限は下回る
+        ts.push(spread_ticker("150.00", "150.05"));
+        assert!(fixed.check(&ts).is_ok());
+        assert!(matches!(ratio.check(&ts), Err(SignalError::SpreadTooWide { .. })));
+        assert!(fixed.allows_entry(&ts));
+        assert!(!ratio.allows_entry(&ts));
+
+        ts.push(spread_ticker("150.00", "150.06"));
+        assert!(matches!(fixed.check(&ts), Err(SignalError::SpreadTooWide { .. })));
+    }
+
     #[test]
     fn test_ticker_stats_zscore_invalid_field() {
         let ts = TickerStats::from(vec![
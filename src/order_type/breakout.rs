@@ -0,0 +1,264 @@
+use std::{
+    str::FromStr,
+    sync::{Arc, RwLock},
+};
+
+use chrono::Duration;
+use log::{info, trace, warn};
+use rust_decimal::Decimal;
+
+use crate::{
+    consts::MAX_TICKER_BUFFER_SIZE,
+    invoke::{
+        self,
+        gui::{BreakoutUnit, Setting},
+    },
+    middleware::ticker::{BarInterval, Retention, Ticker, TickerStats},
+    order_type::{
+        signal::{Intent, Side},
+        strategy,
+    },
+};
+
+/// ブレイクアウトの判定条件
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BreakoutParams {
+    // 直近window秒、またはwindow本の確定足の高値・安値
+    pub window: u32,
+    pub unit: BreakoutUnit,
+    pub bar: BarInterval,
+    // 高値・安値に上乗せする価格差
+    pub buffer: Decimal,
+    // ブレイク後に高値・安値の外側で続けて受信するtick数
+    pub confirm: usize,
+}
+
+impl BreakoutParams {
+    // 設定値から判定条件を取得する、不正値・window 0の場合はNone
+    pub fn from_setting(setting: &Setting) -> Option<Self> {
+        if setting.breakout_window == 0 || setting.breakout_bar_secs == 0 {
+            return None;
+        }
+        let buffer = Decimal::from_str(setting.breakout_buffer.trim()).ok()?;
+        if buffer < Decimal::ZERO {
+            return None;
+        }
+        Some(BreakoutParams {
+            window: setting.breakout_window,
+            unit: setting.breakout_unit,
+            bar: BarInterval::Time(setting.breakout_bar_secs as i64 * 1_000),
+            buffer: buffer * setting.pip(),
+            confirm: setting.breakout_confirm as usize,
+        })
+    }
+}
+
+// 足で判定する場合に必要な足の集計
+pub fn bar_intervals(setting: &Setting) -> Vec<(BarInterval, usize)> {
+    match BreakoutParams::from_setting(setting) {
+        Some(params) if params.unit == BreakoutUnit::Bars => vec![(params.bar, params.window as usize)],
+        _ => vec![],
+    }
+}
+
+// 秒で判定する場合、window秒遡れるようtickを保持する
+pub fn retention(setting: &Setting) -> Option<Retention> {
+    match BreakoutParams::from_setting(setting) {
+        Some(params) if params.unit == BreakoutUnit::Seconds => Some(Retention::new(
+            params.window as i64 * 1_000_000,
+            MAX_TICKER_BUFFER_SIZE,
+        )),
+        _ => None,
+    }
+}
+
+/// ブレイクアウトによる注文処理を行う
+/// 新規注文後、設定値待機して決済する
+pub fn process(logic_setting: Arc<RwLock<invoke::gui::Data>>, tickers: &TickerStats) {
+    let setting = match logic_setting.read() {
+        Ok(read) => read.setting.clone(),
+        Err(e) => {
+            warn!("failed to read setting: {:?}", e);
+            return;
+        }
+    };
+
+    if let Some(intent) = signal(&setting, tickers) {
+        info!("breakout: {:?}", intent);
+        strategy::execute(intent, logic_setting, tickers);
+    }
+}
+
+// ブレイク判定に使う高値・安値 (mid)
+// 確認tickより前の古い順history_len件で集計し、秒指定で履歴が足りない場合はNone
+fn channel(params: &BreakoutParams, tickers: &TickerStats, history_len: usize, from: &Ticker) -> Option<(Decimal, Decimal)> {
+    let from_at = from.timestamp()?;
+    match params.unit {
+        BreakoutUnit::Seconds => {
+            let start = from_at - Duration::seconds(params.window as i64);
+            if tickers.iter().next()?.timestamp()? > start {
+                trace!("skip: history shorter than {} secs", params.window);
+                return None;
+            }
+            tickers
+                .iter()
+                .take(history_len)
+                .filter(|t| t.timestamp().is_some_and(|at| at >= start))
+                .map(|t| t.mid())
+                .fold(None, |acc, mid| match acc {
+                    Some((high, low)) => Some((mid.max(high), mid.min(low))),
+                    None => Some((mid, mid)),
+                })
+        }
+        BreakoutUnit::Bars => {
+            let Some(series) = tickers.bars(params.bar) else {
+                warn!("bars are not registered: {:?}", params.bar);
+                return None;
+            };
+            // 確認tickより前に更新された新しい順window本で集計する
+            let (count, channel) = series
+                .bars()
+                .iter()
+                .rev()
+                .filter(|b| b.updated_at < from_at)
+                .take(params.window as usize)
+                .fold((0, None), |(count, acc), b| {
+                    let channel = match acc {
+                        Some((high, low)) => (b.mid.high.max(high), b.mid.low.min(low)),
+                        None => (b.mid.high, b.mid.low),
+                    };
+                    (count + 1, Some(channel))
+                });
+            if count < params.window as usize {
+                trace!("skip: {} bars, required {}", count, params.window);
+                return None;
+            }
+            channel
+        }
+    }
+}
+
+/// 注文判定のみを行う、バックテストと共通
+/// midが直近の高値 + buffer を上抜ければ買い、安値 - buffer を下抜ければ売り
+/// confirm > 0 の場合はブレイク後confirm tick続けて外側にあることを確認した時点で1度だけ発注する
+pub fn signal(setting: &Setting, tickers: &TickerStats) -> Option<Intent> {
+    // 不正値は保存時に検証するため、tick毎には警告しない
+    let Some(params) = BreakoutParams::from_setting(setting) else {
+        trace!(
+            "invalid breakout setting: window {}, buffer {}",
+            setting.breakout_window, setting.breakout_buffer
+        );
+        return None;
+    };
+
+    // 古い順: [..history][prev][run (confirm + 1 tick)]、末尾から参照する
+    let run_len = params.confirm + 1;
+    let history_len = tickers.len().checked_sub(run_len).filter(|n| *n > 0)?;
+    let run = || tickers.iter().rev().take(run_len);
+    let prev = tickers.iter().rev().nth(run_len)?;
+    let from = tickers.iter().rev().nth(params.confirm)?;
+
+    let (high, low) = channel(&params, tickers, history_len, from)?;
+    let upper = high + params.buffer;
+    let lower = low - params.buffer;
+    trace!("breakout channel: {} - {}", lower, upper);
+
+    let side = if run().all(|t| t.mid() > upper) && prev.mid() <= upper {
+        Side::Buy
+    } else if run().all(|t| t.mid() < lower) && prev.mid() >= lower {
+        Side::Sell
+    } else {
+        return None;
+    };
+
+    if !setting.spread_gate().allows_entry(tickers) {
+        return None;
+    }
+
+    Some(Intent::EntryExit(side))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::{TimeZone, Utc};
+
+    // 100ms間隔、mid = price + 0.001
+    fn tickers(prices: &[i64]) -> TickerStats {
+        let base = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+        let mut stats = TickerStats::new();
+        stats.add_bars(BarInterval::S1, 10);
+        for (i, p) in prices.iter().enumerate() {
+            let bid = Decimal::new(150_000 + p, 3);
+            stats.push(Ticker {
+                bid,
+                ask: bid + Decimal::new(2, 3),
+                recived_at: Some(base + Duration::milliseconds(i as i64 * 100)),
+                ..Default::default()
+            });
+        }
+        stats
+    }
+
+    fn setting(window: u32, unit: BreakoutUnit) -> Setting {
+        let mut setting = Setting::new();
+        setting.order_type = crate::invoke::gui::OrderType::Breakout;
+        setting.breakout_window = window;
+        setting.breakout_unit = unit;
+        setting.breakout_bar_secs = 1;
+        setting
+    }
+
+    // 2秒間 -5〜+5 で推移した後、上抜ける
+    fn range_then(last: &[i64]) -> Vec<i64> {
+        let mut prices: Vec<i64> = (0..20).map(|i| if i % 2 == 0 { 5 } else { -5 }).collect();
+        prices.extend_from_slice(last);
+        prices
+    }
+
+    #[test]
+    fn test_breakout_seconds() {
+        let s = setting(1, BreakoutUnit::Seconds);
+        assert_eq!(signal(&s, &tickers(&range_then(&[6]))), Some(Intent::EntryExit(Side::Buy)));
+        assert_eq!(signal(&s, &tickers(&range_then(&[-6]))), Some(Intent::EntryExit(Side::Sell)));
+        assert_eq!(signal(&s, &tickers(&range_then(&[5]))), None);
+        // 高値を更新しないtickでは発注しない
+        assert_eq!(signal(&s, &tickers(&range_then(&[6, 6]))), None);
+
+        // 履歴が足りない
+        assert_eq!(signal(&setting(5, BreakoutUnit::Seconds), &tickers(&range_then(&[6]))), None);
+    }
+
+    #[test]
+    fn test_breakout_buffer_and_confirm() {
+        // 0.5pip (0.005) のバッファ
+        let mut s = setting(1, BreakoutUnit::Seconds);
+        s.breakout_buffer = "0.5".to_string();
+        assert_eq!(signal(&s, &tickers(&range_then(&[8]))), None);
+        assert_eq!(signal(&s, &tickers(&range_then(&[11]))), Some(Intent::EntryExit(Side::Buy)));
+
+        // 2tick続けて外側にあることを確認する
+        let mut s = setting(1, BreakoutUnit::Seconds);
+        s.breakout_confirm = 2;
+        assert_eq!(signal(&s, &tickers(&range_then(&[6, 7]))), None);
+        assert_eq!(signal(&s, &tickers(&range_then(&[6, 7, 8]))), Some(Intent::EntryExit(Side::Buy)));
+        assert_eq!(signal(&s, &tickers(&range_then(&[6, 0, 8]))), None);
+    }
+
+    #[test]
+    fn test_breakout_bars() {
+        let s = setting(2, BreakoutUnit::Bars);
+        assert_eq!(bar_intervals(&s), vec![(BarInterval::S1, 2)]);
+        assert!(bar_intervals(&setting(2, BreakoutUnit::Seconds)).is_empty());
+        assert_eq!(retention(&s), None);
+        assert_eq!(
+            retention(&setting(60, BreakoutUnit::Seconds)),
+            Some(Retention::new(60_000_000, MAX_TICKER_BUFFER_SIZE))
+        );
+
+        // 1秒足2本の高値を上抜ける
+        assert_eq!(signal(&s, &tickers(&range_then(&[0, 6]))), Some(Intent::EntryExit(Side::Buy)));
+        // 足が足りない
+        assert_eq!(signal(&setting(5, BreakoutUnit::Bars), &tickers(&range_then(&[0, 6]))), None);
+    }
+}
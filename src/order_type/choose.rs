@@ -1,53 +1,88 @@
 use std::sync::mpsc::{self, SyncSender};
-use std::sync::{Arc, RwLock};
+use std::sync::{Arc, Mutex, RwLock};
 
 use crate::{
-    invoke::gui::{Data, OrderType},
-    middleware::ticker::TickerStats,
-    order_type::{entry, exit, origin, simple},
+    invoke::gui::Data,
+    middleware::{
+        latency::{LatencyMonitor, LatencySnapshot},
+        sanitizer::{Sanitizer, SanitizerConfig},
+        ticker::{PushOutcome, SequencePolicy, SymbolBook, Ticker, TickerStats},
+    },
+    order_type::{
+        scheduler::ExitScheduler,
+        strategy::{Strategy, StrategyRegistry},
+    },
 };
 
 use log::{trace, warn};
 
 pub struct OrderRequest {
-    pub order_type: OrderType,
+    pub strategy: Arc<dyn Strategy>,
     pub setting: Arc<RwLock<Data>>,
     pub tickers: TickerStats,
 }
 
 pub struct OrderDispatcher {
     tx: SyncSender<OrderRequest>,
+    registry: StrategyRegistry,
+    scheduler: ExitScheduler,
+    // 受信tickの異常値除外、急変判定の状態を受信間で保持する
+    sanitizer: Mutex<Sanitizer>,
+    // 受信遅延の集計、遅延上限の判定と除外件数もこのモニターで行う
+    latency: Mutex<LatencyMonitor>,
+    // 重複・遅延tickの扱い
+    sequence: SequencePolicy,
 }
 
 impl OrderDispatcher {
     pub fn new() -> Self {
+        OrderDispatcher::with_registry(StrategyRegistry::default())
+    }
+
+    // ホストアプリで登録した注文ロジックを使用する
+    pub fn with_registry(registry: StrategyRegistry) -> Self {
         let (tx, rx) = mpsc::sync_channel::<OrderRequest>(1);
 
         std::thread::spawn(move || {
             for request in rx {
-                match request.order_type {
-                    OrderType::Simple => {
-                        simple::process(request.setting, &request.tickers);
-                    }
-                    OrderType::BuyEntry | OrderType::SellEntry => {
-                        entry::process(request.order_type, request.setting, &request.tickers);
-                    }
-                    OrderType::ExitOnly => {
-                        exit::process(request.setting, &request.tickers);
-                    }
-                    OrderType::Custom => {
-                        origin::process(request.setting, &request.tickers);
-                    }
-                }
+                request.strategy.process(request.setting, &request.tickers);
             }
         });
 
-        OrderDispatcher { tx }
+        OrderDispatcher {
+            tx,
+            registry,
+            scheduler: ExitScheduler::new(),
+            sanitizer: Mutex::new(Sanitizer::default()),
+            latency: Mutex::new(LatencyMonitor::default()),
+            sequence: SequencePolicy::default(),
+        }
+    }
+
+    pub fn with_sanitizer(mut self, config: SanitizerConfig) -> Self {
+        self.sanitizer = Mutex::new(Sanitizer::new(config));
+        self
+    }
+
+    pub fn with_sequence_policy(mut self, policy: SequencePolicy) -> Self {
+        self.sequence = policy;
+        self
+    }
+
+    pub fn registry(&self) -> &StrategyRegistry {
+        &self.registry
+    }
+
+    pub fn latency(&self) -> LatencySnapshot {
+        self.latency.lock().map(|m| m.snapshot()).unwrap_or_default()
     }
 
     pub fn dispatch(&self, logic_setting: Arc<RwLock<Data>>, tickers: &TickerStats) {
+        // 決済待ちの建玉はtick毎に判定し、決済はExitSchedulerのスレッドで行う
+        self.scheduler.on_tick(&logic_setting, tickers.last());
+
         // Check preconditions before sending
-        let order_type = {
+        let strategy = {
             let read_setting = match logic_setting.read() {
                 Ok(setting) => setting,
                 Err(e) => {
@@ -66,11 +101,32 @@ impl OrderDispatcher {
                 return;
             }
 
-            read_setting.setting.order_type
+            // 受信遅延が上限を超えている場合、価格差は意味を持たないため判定しない
+            if let (Some(limit), Some(last)) = (read_setting.setting.stale_micros(), tickers.last()) {
+                let checked = match self.latency.lock() {
+                    Ok(mut latency) => latency.check(last, limit),
+                    Err(e) => {
+                        warn!("failed to lock latency monitor: {:?}", e);
+                        Ok(())
+                    }
+                };
+                if let Err(e) = checked {
+                    trace!("skipping: {}", e);
+                    return;
+                }
+            }
+
+            match self.registry.resolve(&read_setting.setting) {
+                Some(strategy) => strategy,
+                None => {
+                    warn!("unknown strategy: {}", read_setting.setting.strategy_name());
+                    return;
+                }
+            }
         };
 
         let request = OrderRequest {
-            order_type,
+            strategy,
             setting: logic_setting,
             tickers: tickers.clone(),
         };
@@ -80,11 +136,99 @@ impl OrderDispatcher {
             trace!("worker busy, skipping tick");
         }
     }
+
+    /// CORE: 受信tickの遅延を記録・検査し、受理したtickのみSymbolBookへ追加して評価する
+    /// 重複・遅延tickはSymbolBook::push_checkedで検査し、最新tickが増えた場合のみ評価する
+    /// 除外件数・遅延の集計は受信毎にStatusへ反映する
+    pub fn receive(&self, logic_setting: Arc<RwLock<Data>>, book: &mut SymbolBook, t: Ticker) {
+        if let Ok(mut latency) = self.latency.lock() {
+            let _ = latency.observe(&t, None);
+        }
+        let symbol = t.symbol.clone();
+        let accepted = match self.sanitizer.lock() {
+            Ok(mut sanitizer) => sanitizer.sanitize(t).accepted,
+            Err(e) => {
+                warn!("failed to lock sanitizer: {:?}", e);
+                vec![]
+            }
+        };
+
+        if accepted.is_empty() {
+            trace!("tick is not accepted: {}", symbol);
+        } else {
+            self.prepare(&logic_setting, book, &symbol);
+            let mut pushed = false;
+            for t in accepted {
+                match book.push_checked(t, self.sequence) {
+                    PushOutcome::Pushed => pushed = true,
+                    outcome => trace!("tick is not pushed: {:?}", outcome),
+                }
+            }
+            if pushed {
+                self.dispatch_symbol(logic_setting.clone(), book, &symbol);
+            }
+        }
+
+        let rejected = self.sanitizer.lock().map(|s| s.counts()).unwrap_or_default();
+        let latency = self.latency();
+        if let Ok(mut rw) = logic_setting.write() {
+            rw.status.update_rejected(rejected);
+            rw.status.update_latency(latency);
+        }
+    }
+
+    // 選択中の注文ロジックが判定に使う足・保持方針をSymbolBookへ反映する
+    // 設定の変更に追従するため受信毎に確認し、未登録・保持数が異なる場合のみ登録する
+    // 保持方針は広げるのみで、設定を戻しても縮めない
+    fn prepare(&self, logic_setting: &Arc<RwLock<Data>>, book: &mut SymbolBook, symbol: &str) {
+        let (intervals, retention) = match logic_setting.read() {
+            Ok(read) => match self.registry.resolve(&read.setting) {
+                Some(strategy) => (strategy.bar_intervals(&read.setting), strategy.retention(&read.setting)),
+                None => return,
+            },
+            Err(e) => {
+                warn!("failed to read setting: {:?}", e);
+                return;
+            }
+        };
+        for (interval, limit) in intervals {
+            if !book.bar_intervals().contains(&(interval, limit)) {
+                book.add_bars(interval, limit);
+            }
+        }
+        if let Some(required) = retention {
+            let current = book.retention(symbol);
+            let covered = current.cover(&required);
+            if covered != current {
+                book.set_retention(symbol, covered);
+            }
+        }
+    }
+
+    /// 受信tickのシンボルに対応するTickerStatsのみを評価する
+    pub fn dispatch_symbol(&self, logic_setting: Arc<RwLock<Data>>, book: &SymbolBook, symbol: &str) {
+        match book.get(symbol) {
+            Some(tickers) => self.dispatch(logic_setting, tickers),
+            None => trace!("no tickers for symbol: {}", symbol),
+        }
+    }
 }
 
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::consts::DEFAULT_TICKER_BUFFER_SIZE;
+    use crate::invoke::gui::{BreakoutUnit, OrderType};
+    use crate::order_type::{
+        breakout, position, process,
+        signal::{Intent, Side},
+        stops,
+        strategy::Context,
+    };
+    use chrono::{TimeZone, Utc};
+    use rust_decimal::Decimal;
+    use std::sync::atomic::{AtomicUsize, Ordering};
+    use std::time::Duration;
 
     #[test]
     fn test_order_dispatcher_creation() {
@@ -116,4 +260,305 @@ mod tests {
         dispatcher.dispatch(setting, &tickers);
         // Should skip without error
     }
+
+    // ワーカーで実行された回数を数える注文ロジック
+    struct Probe(Arc<AtomicUsize>);
+
+    impl Strategy for Probe {
+        fn name(&self) -> &str {
+            "probe"
+        }
+
+        fn signal(&self, _ctx: &Context) -> Option<Intent> {
+            None
+        }
+
+        fn process(&self, _logic_setting: Arc<RwLock<Data>>, _tickers: &TickerStats) {
+            self.0.fetch_add(1, Ordering::SeqCst);
+        }
+    }
+
+    #[test]
+    fn test_dispatch_stale_tick() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let mut registry = StrategyRegistry::default();
+        registry.register(Probe(count.clone()));
+        let dispatcher = OrderDispatcher::with_registry(registry);
+        let mut data = Data::default();
+        data.status.is_running = true;
+        data.setting.stale_ms = 100;
+        data.setting.strategy = "probe".to_string();
+        let setting = Arc::new(RwLock::new(data));
+        let tick = |diff_micros| Ticker {
+            diff_micros: Some(diff_micros),
+            ..Default::default()
+        };
+
+        // 遅延上限を超えたtickはワーカーへ送信しない
+        let mut tickers = TickerStats::new();
+        tickers.push(tick(500_000));
+        dispatcher.dispatch(setting.clone(), &tickers);
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+        assert!(!setting.read().unwrap().status.is_processing);
+
+        // 上限内のtickは送信する
+        tickers.push(tick(50_000));
+        dispatcher.dispatch(setting.clone(), &tickers);
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        // 判定しなかったtickはモニターで数える
+        assert_eq!(dispatcher.latency().stale, 1);
+    }
+
+    #[test]
+    fn test_receive_corrects_skew_and_reports_latency() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let mut registry = StrategyRegistry::default();
+        registry.register(Probe(count.clone()));
+        let dispatcher = OrderDispatcher::with_registry(registry);
+        let mut data = Data::default();
+        data.status.is_running = true;
+        data.setting.stale_ms = 100;
+        data.setting.strategy = "probe".to_string();
+        let setting = Arc::new(RwLock::new(data));
+        let mut book = SymbolBook::default();
+        let tick = |diff_micros: i64| Ticker {
+            symbol: "USDJPY".to_string(),
+            bid: Decimal::new(150_000, 3),
+            ask: Decimal::new(150_002, 3),
+            diff_micros: Some(diff_micros),
+            ..Default::default()
+        };
+
+        // サーバー時刻が300ms進んでいる: ずれを差し引いた遅延で判定する
+        dispatcher.receive(setting.clone(), &mut book, tick(-300_000));
+        std::thread::sleep(Duration::from_millis(50));
+        dispatcher.receive(setting.clone(), &mut book, tick(-250_000));
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+
+        // ずれを差し引いて150msの遅延は判定しない
+        dispatcher.receive(setting.clone(), &mut book, tick(-150_000));
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(count.load(Ordering::SeqCst), 2);
+
+        let latency = setting.read().unwrap().status.latency;
+        assert_eq!(latency.count, 3);
+        assert_eq!(latency.skew_micros, Some(-300_000));
+        assert_eq!(latency.stale, 1);
+        assert_eq!(latency, dispatcher.latency());
+    }
+
+    #[test]
+    fn test_receive_rejects_crossed_quote() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let mut registry = StrategyRegistry::default();
+        registry.register(Probe(count.clone()));
+        let dispatcher = OrderDispatcher::with_registry(registry);
+        let mut data = Data::default();
+        data.status.is_running = true;
+        data.setting.strategy = "probe".to_string();
+        let setting = Arc::new(RwLock::new(data));
+        let mut book = SymbolBook::default();
+        let tick = |bid: i64, ask: i64| Ticker {
+            symbol: "USDJPY".to_string(),
+            bid: Decimal::new(bid, 3),
+            ask: Decimal::new(ask, 3),
+            ..Default::default()
+        };
+
+        // bid > ask はTickerStatsへ追加せず、ワーカーへも送信しない
+        dispatcher.receive(setting.clone(), &mut book, tick(150_005, 150_000));
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+        assert!(book.get("USDJPY").is_none());
+        assert_eq!(setting.read().unwrap().status.rejected.crossed, 1);
+
+        dispatcher.receive(setting.clone(), &mut book, tick(150_000, 150_002));
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        assert_eq!(book.get("USDJPY").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_receive_skips_duplicate_tick() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let mut registry = StrategyRegistry::default();
+        registry.register(Probe(count.clone()));
+        let dispatcher = OrderDispatcher::with_registry(registry);
+        let mut data = Data::default();
+        data.status.is_running = true;
+        data.setting.strategy = "probe".to_string();
+        let setting = Arc::new(RwLock::new(data));
+        let mut book = SymbolBook::default();
+        let tick = Ticker {
+            symbol: "USDJPY".to_string(),
+            bid: Decimal::new(150_000, 3),
+            ask: Decimal::new(150_002, 3),
+            server_at: Some(Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap()),
+            ..Default::default()
+        };
+
+        // 再送された同一tickは追加せず、評価もしない
+        for _ in 0..2 {
+            dispatcher.receive(setting.clone(), &mut book, tick.clone());
+            std::thread::sleep(Duration::from_millis(50));
+        }
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+        assert_eq!(book.get("USDJPY").unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_receive_registers_strategy_bars() {
+        let dispatcher = OrderDispatcher::new();
+        let mut data = Data::default();
+        data.setting.order_type = OrderType::Breakout;
+        data.setting.breakout_unit = BreakoutUnit::Bars;
+        let setting = Arc::new(RwLock::new(data));
+        let mut book = SymbolBook::default();
+        let tick = Ticker {
+            symbol: "USDJPY".to_string(),
+            bid: Decimal::new(150_000, 3),
+            ask: Decimal::new(150_002, 3),
+            ..Default::default()
+        };
+
+        // 足指定のブレイクアウトは受信時に足の集計を登録する
+        dispatcher.receive(setting.clone(), &mut book, tick.clone());
+        let expected = breakout::bar_intervals(&setting.read().unwrap().setting);
+        assert_eq!(expected.len(), 1);
+        assert_eq!(book.bar_intervals(), expected.as_slice());
+        assert!(book.get("USDJPY").unwrap().bars(expected[0].0).is_some());
+
+        // 設定の変更に追従する
+        setting.write().unwrap().setting.breakout_window += 1;
+        dispatcher.receive(setting.clone(), &mut book, tick);
+        let (interval, limit) = book.bar_intervals()[0];
+        assert_eq!((interval, limit), (expected[0].0, expected[0].1 + 1));
+    }
+
+    #[test]
+    fn test_receive_retains_breakout_window() {
+        let dispatcher = OrderDispatcher::new();
+        let setting = Arc::new(RwLock::new(Data::default()));
+        setting.write().unwrap().setting.order_type = OrderType::Breakout;
+        let window = setting.read().unwrap().setting.breakout_window as i64;
+        let mut book = SymbolBook::default();
+        let base = Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+
+        // 100ms間隔で既定の保持数を超えても、秒指定のwindow秒分は保持する
+        let n = window * 10 + 10;
+        for i in 0..n {
+            let t = Ticker {
+                symbol: "USDJPY".to_string(),
+                bid: Decimal::new(150_000, 3),
+                ask: Decimal::new(150_002, 3),
+                recived_at: Some(base + chrono::Duration::milliseconds(i * 100)),
+                ..Default::default()
+            };
+            dispatcher.receive(setting.clone(), &mut book, t);
+        }
+        let stats = book.get("USDJPY").unwrap();
+        assert!(stats.len() > DEFAULT_TICKER_BUFFER_SIZE);
+        let oldest = stats.iter().next().unwrap().timestamp().unwrap();
+        let latest = stats.last().unwrap().timestamp().unwrap();
+        assert!(latest - oldest >= chrono::Duration::seconds(window));
+        assert_eq!(book.retention("USDJPY").min_micros, window * 1_000_000);
+    }
+
+    #[test]
+    fn test_dispatch_unknown_strategy() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let mut registry = StrategyRegistry::default();
+        registry.register(Probe(count.clone()));
+        let dispatcher = OrderDispatcher::with_registry(registry);
+        let mut data = Data::default();
+        data.status.is_running = true;
+        data.setting.strategy = "unknown".to_string();
+        let setting = Arc::new(RwLock::new(data));
+        let tickers = TickerStats::new();
+
+        // 未登録の注文ロジックはワーカーへ送信しない
+        dispatcher.dispatch(setting.clone(), &tickers);
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+
+        // 登録済みの名前であれば送信する
+        setting.write().unwrap().setting.strategy = "probe".to_string();
+        dispatcher.dispatch(setting.clone(), &tickers);
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
+
+    #[test]
+    fn test_dispatch_exits_watched_position() {
+        let dispatcher = OrderDispatcher::new();
+        let mut data = Data::default();
+        data.setting.trailing_stop = "3".to_string();
+        data.mouse_exit.n = 0;
+        let setting = Arc::new(RwLock::new(data));
+        position::open(&setting, Side::Buy).unwrap();
+        process::lock(setting.clone());
+        stops::watch(setting.clone(), "USDJPY", Side::Buy, Decimal::new(150_000, 3), 60_000);
+
+        let tick = |bid: i64| Ticker {
+            symbol: "USDJPY".to_string(),
+            bid: Decimal::new(bid, 3),
+            ask: Decimal::new(bid, 3),
+            ..Default::default()
+        };
+        // 他シンボルの価格では決済しない
+        let mut others = TickerStats::new();
+        others.push(Ticker {
+            symbol: "EURUSD".to_string(),
+            ..tick(1_080)
+        });
+        dispatcher.dispatch(setting.clone(), &others);
+        assert!(setting.read().unwrap().status.watch.is_some());
+
+        // 150.100まで上昇後、0.03戻した150.070で決済する
+        let mut tickers = TickerStats::new();
+        for bid in [150_050, 150_100, 150_080] {
+            tickers.push(tick(bid));
+            dispatcher.dispatch(setting.clone(), &tickers);
+        }
+        assert!(setting.read().unwrap().status.watch.is_some());
+
+        tickers.push(tick(150_070));
+        dispatcher.dispatch(setting.clone(), &tickers);
+        std::thread::sleep(Duration::from_millis(200));
+
+        let read = setting.read().unwrap();
+        assert!(read.status.watch.is_none());
+        assert!(!read.status.is_processing);
+        assert_eq!(read.status.orders.back().unwrap().side, "buy");
+        assert_eq!(read.status.position, position::PositionState::Flat);
+    }
+
+    #[test]
+    fn test_dispatch_symbol_unknown() {
+        let count = Arc::new(AtomicUsize::new(0));
+        let mut registry = StrategyRegistry::default();
+        registry.register(Probe(count.clone()));
+        let dispatcher = OrderDispatcher::with_registry(registry);
+        let mut data = Data::default();
+        data.status.is_running = true;
+        data.setting.strategy = "probe".to_string();
+        let setting = Arc::new(RwLock::new(data));
+        let mut book = SymbolBook::default();
+        book.push(Ticker {
+            symbol: "EURUSD".to_string(),
+            ..Default::default()
+        });
+
+        // 未登録シンボルはワーカーへ送信しない
+        dispatcher.dispatch_symbol(setting.clone(), &book, "USDJPY");
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(count.load(Ordering::SeqCst), 0);
+
+        dispatcher.dispatch_symbol(setting.clone(), &book, "EURUSD");
+        std::thread::sleep(Duration::from_millis(50));
+        assert_eq!(count.load(Ordering::SeqCst), 1);
+    }
 }
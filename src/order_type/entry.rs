@@ -2,13 +2,15 @@ use std::sync::{Arc, RwLock};
 
 use crate::{
     invoke,
-    invoke::gui::OrderType,
+    invoke::gui::{OrderType, Setting},
     middleware::{mouse, ticker::TickerStats},
-    order_type::process,
+    order_type::{
+        position, process,
+        signal::{Intent, Side},
+    },
 };
 
-use log::info;
-use rust_decimal::{prelude::Zero, Decimal};
+use log::{info, trace};
 
 /// シンプルな注文及び決済処理を行う
 /// 指定時間遡り、直近のTicker mid値と現在のTicker mid値の差分を計算し、設定値以上差が生じれば注文を行う
@@ -31,32 +33,17 @@ pub fn process(t: OrderType, logic_setting: Arc<RwLock<invoke::gui::Data>>, tick
             read.mouse_entry_sell.clone(),
         )
     };
-    let (target_diff_micros, target_diff_ticks) = setting.get();
 
-    let diff = tickers.diff(target_diff_micros);
-    if target_diff_ticks < diff.abs() {
+    // 建玉中は追加のエントリーを行わない
+    if let Some(Intent::Entry(side)) = signal(t, &setting, tickers) {
+        if position::open(&logic_setting, side).is_err() {
+            process::unlock(logic_setting.clone(), None);
+            return;
+        }
         // order_type BuyEntry: 買い注文, SellEntry: 売り注文
-        let entry_mouse = match t {
-            OrderType::BuyEntry => {
-                if diff < Decimal::zero() {
-                    info!("failed miss match order_type & trade side: {:?}", t);
-                    return;
-                }
-
-                mouse_entry_buy
-            }
-            OrderType::SellEntry => {
-                if diff > Decimal::zero() {
-                    info!("failed miss match order_type & trade side: {:?}", t);
-                    return;
-                }
-
-                mouse_entry_sell
-            }
-            _ => {
-                info!("failed order_type: {:?}", t);
-                return;
-            }
+        let entry_mouse = match side {
+            Side::Buy => mouse_entry_buy,
+            Side::Sell => mouse_entry_sell,
         };
 
         // 新規注文のマウス操作
@@ -66,3 +53,39 @@ pub fn process(t: OrderType, logic_setting: Arc<RwLock<invoke::gui::Data>>, tick
 
     process::unlock(logic_setting.clone(), None);
 }
+
+/// 注文判定のみを行う、バックテストと共通
+/// 差分の方向がorder_typeと一致する場合のみエントリーする
+pub fn signal(t: OrderType, setting: &Setting, tickers: &TickerStats) -> Option<Intent> {
+    let (target_diff_micros, target_diff_ticks) = setting.get();
+
+    let diff = match tickers.diff(target_diff_micros) {
+        Ok(diff) => diff,
+        Err(e) => {
+            trace!("skip: {}", e);
+            return None;
+        }
+    };
+    if target_diff_ticks >= diff.abs() {
+        return None;
+    }
+
+    if !setting.spread_gate().allows_entry(tickers) {
+        return None;
+    }
+
+    let side = match t {
+        OrderType::BuyEntry => Side::Buy,
+        OrderType::SellEntry => Side::Sell,
+        _ => {
+            info!("failed order_type: {:?}", t);
+            return None;
+        }
+    };
+    if Side::from_diff(diff) != Some(side) {
+        info!("failed miss match order_type & trade side: {:?}", t);
+        return None;
+    }
+
+    Some(Intent::Entry(side))
+}
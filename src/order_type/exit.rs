@@ -1,12 +1,12 @@
 use std::sync::{Arc, RwLock};
 
 use crate::{
-    invoke,
+    invoke::{self, gui::Setting},
     middleware::{mouse, ticker::TickerStats, utils},
-    order_type::process,
+    order_type::{position, process, signal::Intent},
 };
 
-use log::info;
+use log::{info, trace};
 
 /// 決済のみの注文を行う
 pub fn process(logic_setting: Arc<RwLock<invoke::gui::Data>>, tickers: &TickerStats) {
@@ -26,10 +26,9 @@ pub fn process(logic_setting: Arc<RwLock<invoke::gui::Data>>, tickers: &TickerSt
             read.mouse_exit.clone(),
         )
     };
-    let (target_diff_micros, target_diff_ticks) = setting.get();
 
-    let diff = tickers.diff(target_diff_micros);
-    if target_diff_ticks < diff.abs() {
+    // 建玉がない場合は決済しない
+    if signal(&setting, tickers).is_some() && position::exit(&logic_setting, None).is_ok() {
         // 決済のマウス操作
         let mouse_c = mouse::Mouse::default();
 
@@ -39,7 +38,23 @@ pub fn process(logic_setting: Arc<RwLock<invoke::gui::Data>>, tickers: &TickerSt
             mouse_c.order(&exit_mouse);
             utils::sleep(1, 0);
         }
+        let _ = position::closed(&logic_setting);
     }
 
     process::unlock(logic_setting.clone(), None);
 }
+
+/// 注文判定のみを行う、バックテストと共通
+/// 差分が設定値を超えれば方向を問わず決済する
+pub fn signal(setting: &Setting, tickers: &TickerStats) -> Option<Intent> {
+    let (target_diff_micros, target_diff_ticks) = setting.get();
+
+    let diff = match tickers.diff(target_diff_micros) {
+        Ok(diff) => diff,
+        Err(e) => {
+            trace!("skip: {}", e);
+            return None;
+        }
+    };
+    (target_diff_ticks < diff.abs()).then_some(Intent::Exit(None))
+}
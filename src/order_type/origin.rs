@@ -1,12 +1,19 @@
 use std::sync::{Arc, RwLock};
 
 use crate::{
+    error::SignalError,
     invoke,
-    middleware::{mouse, ticker::TickerStats, utils},
-    order_type::process,
+    middleware::ticker::{Ticker, TickerStats},
+    order_type::{
+        position, process,
+        signal::{Intent, Side},
+        stops,
+    },
 };
 
-use log::{info, warn};
+use log::{info, trace, warn};
+
+pub use crate::middleware::command::{Action, Command};
 
 enum Flag {
     None,
@@ -38,55 +45,138 @@ impl From<u8> for Flag {
     }
 }
 
+impl Flag {
+    fn intent(&self) -> Option<Intent> {
+        match self {
+            Flag::None => None,
+            Flag::EntryBuy => Some(Intent::Entry(Side::Buy)),
+            Flag::EntrySell => Some(Intent::Entry(Side::Sell)),
+            Flag::EntryBuyExit => Some(Intent::EntryExit(Side::Buy)),
+            Flag::EntrySellExit => Some(Intent::EntryExit(Side::Sell)),
+            Flag::ExitBuy => Some(Intent::Exit(Some(Side::Buy))),
+            Flag::ExitSell => Some(Intent::Exit(Some(Side::Sell))),
+        }
+    }
+}
+
+/// commandを建玉に合わせて展開した注文手順
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Step {
+    // 決済、volumeがNoneの場合は全数量
+    Exit { side: Option<Side>, volume: Option<u32> },
+    Entry { side: Side, volume: u32, hold_ms: Option<u64> },
+}
+
+impl Command {
+    // CORE: 保有中の建玉 (方向, 数量) から注文手順を決める
+    // 建玉の状態に合わない手順は実行時にPositionStateが拒否する
+    pub fn steps(&self, held: Option<(Side, u32)>) -> Result<Vec<Step>, SignalError> {
+        if self.volume == Some(0) {
+            return Err(SignalError::InvalidCommand("volume must be positive".to_string()));
+        }
+        let side = || {
+            self.side
+                .ok_or_else(|| SignalError::InvalidCommand(format!("{:?} requires side", self.action)))
+        };
+        let entry = |side: Side, volume: u32| Step::Entry {
+            side,
+            volume,
+            hold_ms: self.hold_ms,
+        };
+
+        let steps = match self.action {
+            Action::Entry => vec![entry(side()?, self.volume.unwrap_or(1))],
+            Action::Exit => vec![Step::Exit {
+                side: self.side,
+                volume: None,
+            }],
+            Action::CloseAll => vec![Step::Exit { side: None, volume: None }],
+            Action::PartialClose => {
+                let volume = self
+                    .volume
+                    .ok_or_else(|| SignalError::InvalidCommand("PartialClose requires volume".to_string()))?;
+                // 保有数量以上は全数量を決済する
+                let partial = held.is_none_or(|(_, held)| volume < held);
+                vec![Step::Exit {
+                    side: self.side,
+                    volume: partial.then_some(volume),
+                }]
+            }
+            Action::Reverse => match held {
+                // 建玉なしは新規のみ
+                None => vec![entry(side()?, self.volume.unwrap_or(1))],
+                Some((held_side, held_volume)) => {
+                    let side = self.side.unwrap_or(held_side.opposite());
+                    if side == held_side {
+                        return Err(SignalError::InvalidCommand(format!("Reverse to held side {}", side.as_str())));
+                    }
+                    vec![
+                        Step::Exit {
+                            side: Some(held_side),
+                            volume: None,
+                        },
+                        entry(side, self.volume.unwrap_or(held_volume)),
+                    ]
+                }
+            },
+        };
+        Ok(steps)
+    }
+}
+
 /// フラグを受け取り、処理を分岐する
 /// 注文可否の判定は親関数で行う
 pub fn process(_logic_setting: Arc<RwLock<invoke::gui::Data>>, tickers: &TickerStats) {
-    let lastest_ticker = match tickers.last() {
-        Some(ticker) => ticker,
-        None => {
-            info!("no ticker data");
-            return;
-        }
+    let Some(lastest_ticker) = tickers.last() else {
+        info!("no ticker data");
+        return;
     };
 
+    // commandがあればflagより優先する
+    if let Some(command) = command(tickers) {
+        run(command, lastest_ticker, _logic_setting);
+        return;
+    }
+
     info!("flag: {}", lastest_ticker.flag());
 
-    #[allow(unreachable_code)]
-    match Flag::from(lastest_ticker.flag()) {
-        Flag::None => {
+    match signal(tickers) {
+        None => {
             info!("undefined flag");
         }
-        Flag::EntryBuy => {
-            info!("switch to entry buy");
-            entry(true, _logic_setting);
-        }
-        Flag::EntrySell => {
-            info!("switch to entry sell");
-            entry(false, _logic_setting);
-        }
-        Flag::EntryBuyExit => {
-            info!("switch to buy entry, wait until exit logic");
-            entry_and_exit(true, _logic_setting);
+        Some(Intent::Entry(side)) => {
+            info!("switch to entry {}", side.as_str());
+            entry(side, 1, _logic_setting);
         }
-        Flag::EntrySellExit => {
-            info!("switch to sell entry, wait until exit logic");
-            entry_and_exit(false, _logic_setting);
+        Some(Intent::EntryExit(side)) => {
+            info!("switch to {} entry, wait until exit logic", side.as_str());
+            entry_and_exit(side, 1, lastest_ticker, None, _logic_setting);
         }
-        Flag::ExitBuy => {
-            info!("switch to buy exit only logic");
-            exit(true, _logic_setting);
-        }
-        Flag::ExitSell => {
-            info!("switch to sell exit only logic");
-            exit(false, _logic_setting);
+        Some(Intent::Exit(side)) => {
+            info!("switch to {} exit only logic", side.map_or("any", |s| s.as_str()));
+            exit(side, None, _logic_setting);
         }
     }
 }
 
-fn entry(is_buy: bool, logic_setting: Arc<RwLock<invoke::gui::Data>>) {
-    process::lock(logic_setting.clone());
+/// 注文判定のみを行う、バックテストと共通
+/// 最新tickのフラグをそのまま注文内容とする
+pub fn signal(tickers: &TickerStats) -> Option<Intent> {
+    tickers.last().and_then(|t| Flag::from(t.flag()).intent())
+}
 
-    let entry_mouse = {
+/// 最新tickの注文指示、バックテストと共通
+/// command.sideがなければtickのsideを使用する
+pub fn command(tickers: &TickerStats) -> Option<Command> {
+    let t = tickers.last()?;
+    let mut command = t.command.clone()?;
+    command.side = command.side.or(t.side());
+    Some(command)
+}
+
+// 注文指示を建玉に合わせて実行する
+fn run(command: Command, t: &Ticker, logic_setting: Arc<RwLock<invoke::gui::Data>>) {
+    let held = {
         let read = match logic_setting.read() {
             Ok(setting) => setting,
             Err(e) => {
@@ -94,87 +184,141 @@ fn entry(is_buy: bool, logic_setting: Arc<RwLock<invoke::gui::Data>>) {
                 return;
             }
         };
-
-        if is_buy {
-            read.mouse_entry_buy.clone()
-        } else {
-            read.mouse_entry_sell.clone()
+        // 同じidは再送とみなす
+        if command.id.is_some() && command.id == read.status.signal_id {
+            trace!("skip duplicate command: {:?}", command.id);
+            return;
         }
+        read.status.position.side().map(|side| (side, read.status.volume.max(1)))
     };
 
-    // 新規注文のマウス操作
-    let mouse_c = mouse::Mouse::default();
-    mouse_c.order(&entry_mouse);
-
-    process::unlock(logic_setting.clone(), None);
+    let steps = match command.steps(held) {
+        Ok(steps) => steps,
+        Err(e) => {
+            warn!("{}", e);
+            if let Ok(mut rw) = logic_setting.write() {
+                rw.status.message = e.to_string();
+            }
+            return;
+        }
+    };
+    info!("command {:?} id {}: {:?}", command.action, command.id.as_deref().unwrap_or("-"), steps);
+
+    // 決済できなかった場合は途転の新規を行わない
+    let done = steps.into_iter().all(|step| match step {
+        Step::Exit { side, volume } => exit(side, volume, logic_setting.clone()),
+        Step::Entry {
+            side,
+            volume,
+            hold_ms: None,
+        } => entry(side, volume, logic_setting.clone()),
+        Step::Entry { side, volume, hold_ms } => {
+            entry_and_exit(side, volume, t, hold_ms, logic_setting.clone())
+        }
+    });
+    // 全ての手順を実行した場合のみidを記録し、拒否された指示の再送は実行する
+    if !done {
+        return;
+    }
+    if let (Some(id), Ok(mut rw)) = (command.id, logic_setting.write()) {
+        rw.status.signal_id = Some(id);
+    }
 }
 
-fn entry_and_exit(is_buy: bool, logic_setting: Arc<RwLock<invoke::gui::Data>>) {
-    process::lock(logic_setting.clone());
+fn entry_mouse(side: Side, logic_setting: &Arc<RwLock<invoke::gui::Data>>) -> Option<(invoke::gui::Mouse, u64)> {
+    let read = match logic_setting.read() {
+        Ok(setting) => setting,
+        Err(e) => {
+            warn!("failed to read setting: {:?}", e);
+            return None;
+        }
+    };
 
-    let (readed_setting, readed_entry_mouse, readed_exit_mouse) = {
-        let read = match logic_setting.read() {
-            Ok(setting) => setting,
-            Err(e) => {
-                warn!("failed to read setting: {:?}", e);
-                return;
-            }
-        };
+    let mouse = match side {
+        Side::Buy => read.mouse_entry_buy.clone(),
+        Side::Sell => read.mouse_entry_sell.clone(),
+    };
+    Some((mouse, read.setting.get_sleep_ms()))
+}
 
-        let readed_setting = read.setting.clone();
-        let entry = if is_buy {
-            read.mouse_entry_buy.clone()
-        } else {
-            read.mouse_entry_sell.clone()
-        };
-        let readed_exit_mouse = read.mouse_exit.clone();
-        (readed_setting, entry, readed_exit_mouse)
+// 設定を読めない場合は建玉を登録しない
+fn entry(side: Side, volume: u32, logic_setting: Arc<RwLock<invoke::gui::Data>>) -> bool {
+    let Some((entry_mouse, _)) = entry_mouse(side, &logic_setting) else {
+        return false;
     };
+    // 建玉中は新規注文を行わない
+    if position::open_volume(&logic_setting, side, volume).is_err() {
+        return false;
+    }
+    process::lock(logic_setting.clone());
 
     // 新規注文のマウス操作
-    let mouse_c = mouse::Mouse::default();
-    mouse_c.order(&readed_entry_mouse);
-
-    // 設定値待機する
-    let target_sleep_ms = readed_setting.get_sleep_ms();
-    utils::sleep(0, target_sleep_ms);
-
-    // 決済注文のマウス操作
-    let n = readed_exit_mouse.n;
-    for _ in 0..n {
-        mouse_c.order(&readed_exit_mouse);
-        utils::sleep(1, 0);
-    }
+    stops::click(&entry_mouse, volume);
 
     process::unlock(logic_setting.clone(), None);
+    true
 }
 
-fn exit(_is_buy: bool, logic_setting: Arc<RwLock<invoke::gui::Data>>) {
+// hold_msがNoneの場合は設定の待機時間を最大保有時間とする
+fn entry_and_exit(
+    side: Side,
+    volume: u32,
+    t: &Ticker,
+    hold_ms: Option<u64>,
+    logic_setting: Arc<RwLock<invoke::gui::Data>>,
+) -> bool {
+    let Some((entry_mouse, sleep_ms)) = entry_mouse(side, &logic_setting) else {
+        return false;
+    };
+    if position::open_volume(&logic_setting, side, volume).is_err() {
+        return false;
+    }
     process::lock(logic_setting.clone());
 
-    let exit_mouse = {
+    // 新規注文のマウス操作
+    stops::click(&entry_mouse, volume);
+
+    // 建玉を監視し、決済はExitSchedulerが行う
+    stops::watch(logic_setting, &t.symbol, side, side.entry_price(t), hold_ms.unwrap_or(sleep_ms));
+    true
+}
+
+// volumeがNoneの場合は全数量を決済する
+fn exit(side: Option<Side>, volume: Option<u32>, logic_setting: Arc<RwLock<invoke::gui::Data>>) -> bool {
+    let (exit_mouse, held) = {
         let read = match logic_setting.read() {
             Ok(setting) => setting,
             Err(e) => {
                 warn!("failed to read setting: {:?}", e);
-                return;
+                return false;
             }
         };
 
-        read.mouse_exit.clone()
+        (read.mouse_exit.clone(), read.status.volume.max(1))
     };
 
-    // 新規注文のマウス操作
-    let mouse_c = mouse::Mouse::default();
-    mouse_c.order(&exit_mouse);
+    // 同じ方向の建玉がない場合は決済しない
+    let refused = match volume {
+        Some(volume) => position::reduce(&logic_setting, side, volume).is_err(),
+        None => position::exit(&logic_setting, side).is_err(),
+    };
+    if refused {
+        return false;
+    }
+    process::lock(logic_setting.clone());
 
+    // 決済注文のマウス操作
+    stops::click_exit(&exit_mouse, volume.unwrap_or(held));
+
+    if volume.is_none() {
+        let _ = position::closed(&logic_setting);
+    }
     process::unlock(logic_setting.clone(), None);
+    true
 }
 
 #[cfg(test)]
 mod test {
-    use crate::middleware::ticker::Ticker;
-
     use super::*;
     use std::env;
 
@@ -296,6 +440,152 @@ mod test {
         process(logic_setting, &tickers);
     }
 
+    fn command_of(action: Action) -> Command {
+        Command {
+            action,
+            side: None,
+            volume: None,
+            hold_ms: None,
+            id: None,
+        }
+    }
+
+    #[test]
+    fn test_command_steps() {
+        let exit_all = Step::Exit { side: None, volume: None };
+
+        // 新規は方向が必要
+        assert!(command_of(Action::Entry).steps(None).is_err());
+        let entry = Command {
+            side: Some(Side::Buy),
+            volume: Some(2),
+            hold_ms: Some(5_000),
+            ..command_of(Action::Entry)
+        };
+        assert_eq!(
+            entry.steps(None).unwrap(),
+            vec![Step::Entry {
+                side: Side::Buy,
+                volume: 2,
+                hold_ms: Some(5_000)
+            }]
+        );
+        assert_eq!(command_of(Action::CloseAll).steps(Some((Side::Sell, 1))).unwrap(), vec![exit_all]);
+
+        // 一部決済は保有数量以上で全数量
+        let partial = Command {
+            volume: Some(1),
+            ..command_of(Action::PartialClose)
+        };
+        assert_eq!(
+            partial.steps(Some((Side::Buy, 3))).unwrap(),
+            vec![Step::Exit {
+                side: None,
+                volume: Some(1)
+            }]
+        );
+        assert_eq!(partial.steps(Some((Side::Buy, 1))).unwrap(), vec![exit_all]);
+        assert!(command_of(Action::PartialClose).steps(Some((Side::Buy, 3))).is_err());
+
+        // 途転は方向の指定がなければ建玉の逆方向へ同じ数量
+        assert_eq!(
+            command_of(Action::Reverse).steps(Some((Side::Buy, 2))).unwrap(),
+            vec![
+                Step::Exit {
+                    side: Some(Side::Buy),
+                    volume: None
+                },
+                Step::Entry {
+                    side: Side::Sell,
+                    volume: 2,
+                    hold_ms: None
+                },
+            ]
+        );
+        let same = Command {
+            side: Some(Side::Buy),
+            ..command_of(Action::Reverse)
+        };
+        assert!(same.steps(Some((Side::Buy, 1))).is_err());
+        assert_eq!(same.steps(None).unwrap().len(), 1);
+    }
+
+    #[test]
+    fn test_command_overrides_flag() {
+        let mut tickers = TickerStats::default();
+        tickers.push(Ticker {
+            flag: Some(3),
+            side: Some(2),
+            command: Some(command_of(Action::Entry)),
+            ..Default::default()
+        });
+        // commandの方向はtickのsideで補う
+        assert_eq!(command(&tickers).unwrap().side, Some(Side::Sell));
+        assert_eq!(signal(&tickers), Some(Intent::EntryExit(Side::Buy)));
+
+        // 同じidは一度だけ、不正な指示はメッセージに残す
+        let logic_setting = Arc::new(RwLock::new(invoke::gui::Data::default()));
+        logic_setting.write().unwrap().status.signal_id = Some("ea-1".to_string());
+        let duplicated = Command {
+            id: Some("ea-1".to_string()),
+            ..command_of(Action::Entry)
+        };
+        run(duplicated, &Ticker::default(), logic_setting.clone());
+        assert_eq!(logic_setting.read().unwrap().status.message, "off");
+        run(command_of(Action::Entry), &Ticker::default(), logic_setting.clone());
+        assert!(logic_setting.read().unwrap().status.message.contains("requires side"));
+        assert_eq!(logic_setting.read().unwrap().status.position, position::PositionState::Flat);
+    }
+
+    #[test]
+    fn test_command_id_recorded_after_steps() {
+        let logic_setting = Arc::new(RwLock::new(invoke::gui::Data::default()));
+        logic_setting.write().unwrap().mouse_entry_buy.n = 0;
+        logic_setting.write().unwrap().status.position = position::PositionState::Exiting(Side::Buy);
+        let entry = Command {
+            side: Some(Side::Buy),
+            id: Some("ea-2".to_string()),
+            ..command_of(Action::Entry)
+        };
+
+        // 決済中で新規を拒否された指示のidは記録しない
+        run(entry.clone(), &Ticker::default(), logic_setting.clone());
+        assert_eq!(logic_setting.read().unwrap().status.signal_id, None);
+
+        // 再送は実行し、実行後にidを記録する
+        logic_setting.write().unwrap().status.position = position::PositionState::Flat;
+        run(entry, &Ticker::default(), logic_setting.clone());
+        let read = logic_setting.read().unwrap();
+        assert_eq!(read.status.position, position::PositionState::Long);
+        assert_eq!(read.status.signal_id.as_deref(), Some("ea-2"));
+    }
+
+    #[test]
+    fn test_command_entry_volume_timed_exit() {
+        let mut data = invoke::gui::Data::default();
+        data.mouse_entry_buy.n = 0;
+        data.mouse_entry_sell.n = 0;
+        data.mouse_exit.n = 0;
+        let logic_setting = Arc::new(RwLock::new(data));
+        let entry = Command {
+            side: Some(Side::Buy),
+            volume: Some(2),
+            hold_ms: Some(0),
+            ..command_of(Action::Entry)
+        };
+        run(entry, &Ticker::default(), logic_setting.clone());
+        assert_eq!(logic_setting.read().unwrap().status.watch.as_ref().unwrap().volume, 2);
+
+        // 最大保有時間の経過後、新規と同じ数量を決済する
+        let (w, reason) = stops::check(&logic_setting, None, chrono::Utc::now()).unwrap();
+        assert_eq!((w.volume, reason), (2, stops::ExitReason::MaxHold));
+        stops::close(logic_setting.clone(), w, reason);
+        let read = logic_setting.read().unwrap();
+        assert_eq!(read.status.position, position::PositionState::Flat);
+        assert_eq!(read.status.volume, 0);
+        assert_eq!(read.status.orders.len(), 1);
+    }
+
     // テスト用のヘルパー関数をTickerStatsに実装する必要があります
     // 例:
     // impl TickerStats {
@@ -0,0 +1,232 @@
+use std::{
+    fmt,
+    sync::{Arc, RwLock},
+};
+
+use log::warn;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    error::SignalError,
+    invoke::gui::{Data, Status},
+    order_type::{
+        signal::{Intent, Side},
+        stops,
+    },
+};
+
+/// 建玉の状態
+/// マウス操作の実行結果で遷移し、ありえない遷移 (建玉中の新規・建玉なしの決済) は拒否する
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub enum PositionState {
+    #[default]
+    Flat,
+    Long,
+    Short,
+    // 決済注文中、完了までは新規・決済とも受け付けない
+    Exiting(Side),
+}
+
+impl fmt::Display for PositionState {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            PositionState::Flat => write!(f, "flat"),
+            PositionState::Long => write!(f, "long"),
+            PositionState::Short => write!(f, "short"),
+            PositionState::Exiting(side) => write!(f, "exiting {}", side.as_str()),
+        }
+    }
+}
+
+impl PositionState {
+    // 保有中の建玉の方向
+    pub fn side(&self) -> Option<Side> {
+        match self {
+            PositionState::Long => Some(Side::Buy),
+            PositionState::Short => Some(Side::Sell),
+            _ => None,
+        }
+    }
+
+    fn refuse(&self, action: String) -> SignalError {
+        SignalError::InvalidTransition {
+            state: self.to_string(),
+            action,
+        }
+    }
+
+    // Flat → Long/Short
+    pub fn open(&mut self, side: Side) -> Result<(), SignalError> {
+        if *self != PositionState::Flat {
+            return Err(self.refuse(format!("entry {}", side.as_str())));
+        }
+        *self = match side {
+            Side::Buy => PositionState::Long,
+            Side::Sell => PositionState::Short,
+        };
+        Ok(())
+    }
+
+    // Long/Short → Exiting、sideを指定した場合は同じ方向の建玉のみ
+    pub fn exit(&mut self, side: Option<Side>) -> Result<Side, SignalError> {
+        match self.side() {
+            Some(held) if side.is_none_or(|s| s == held) => {
+                *self = PositionState::Exiting(held);
+                Ok(held)
+            }
+            _ => Err(self.refuse(format!("exit {}", side.map_or("any", |s| s.as_str())))),
+        }
+    }
+
+    // Exiting → Flat
+    pub fn closed(&mut self) -> Result<(), SignalError> {
+        if !matches!(self, PositionState::Exiting(_)) {
+            return Err(self.refuse("exit done".to_string()));
+        }
+        *self = PositionState::Flat;
+        Ok(())
+    }
+
+    // 状態を変えずにIntentを実行できるか判定する
+    pub fn check(&self, intent: Intent) -> Result<(), SignalError> {
+        let mut next = *self;
+        match intent {
+            Intent::Entry(side) | Intent::EntryExit(side) => next.open(side),
+            Intent::Exit(side) => next.exit(side).map(|_| ()),
+        }
+    }
+}
+
+// 共有状態の建玉を遷移させる、拒否した場合はログとメッセージに残してErrを返す
+fn transition<T>(
+    logic_setting: &Arc<RwLock<Data>>,
+    f: impl FnOnce(&mut Status) -> Result<T, SignalError>,
+) -> Result<T, SignalError> {
+    let mut rw = logic_setting
+        .write()
+        .map_err(|e| SignalError::LockPoisoned(e.to_string()))?;
+    f(&mut rw.status).inspect_err(|e| {
+        warn!("{}", e);
+        rw.status.message = e.to_string();
+    })
+}
+
+/// 新規注文の前に建玉を登録する
+/// 決済待ちの建玉と逆方向の場合は、設定に従い予定の決済を取り消す・置き換える
+pub fn open(logic_setting: &Arc<RwLock<Data>>, side: Side) -> Result<(), SignalError> {
+    open_volume(logic_setting, side, 1)
+}
+
+/// 数量を指定して建玉を登録する
+pub fn open_volume(logic_setting: &Arc<RwLock<Data>>, side: Side, volume: u32) -> Result<(), SignalError> {
+    transition(logic_setting, |s| {
+        s.position.open(side)?;
+        s.volume = volume;
+        Ok(())
+    })
+    .inspect_err(|_| {
+        stops::opposite(logic_setting, side);
+    })
+}
+
+/// 決済注文の前に決済中とし、対象の建玉の方向を返す
+pub fn exit(logic_setting: &Arc<RwLock<Data>>, side: Option<Side>) -> Result<Side, SignalError> {
+    transition(logic_setting, |s| s.position.exit(side))
+}
+
+/// 建玉の一部を決済する前に数量を減らす、保有数量以上の場合は拒否する
+/// 決済待ちの建玉は監視中の数量も減らす
+pub fn reduce(logic_setting: &Arc<RwLock<Data>>, side: Option<Side>, volume: u32) -> Result<Side, SignalError> {
+    transition(logic_setting, |s| {
+        s.position.check(Intent::Exit(side))?;
+        if volume == 0 || volume >= s.volume {
+            return Err(s.position.refuse(format!("partial exit {} of {}", volume, s.volume)));
+        }
+        s.volume -= volume;
+        if let Some(w) = s.watch.as_mut() {
+            w.volume = s.volume;
+        }
+        s.position.side().ok_or_else(|| s.position.refuse("partial exit".to_string()))
+    })
+}
+
+/// 決済注文の完了、建玉の監視も外す
+pub fn closed(logic_setting: &Arc<RwLock<Data>>) -> Result<(), SignalError> {
+    transition(logic_setting, |s| {
+        s.position.closed()?;
+        s.volume = 0;
+        s.watch = None;
+        Ok(())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_position_transitions() {
+        let mut p = PositionState::default();
+        assert!(p.exit(None).is_err());
+        assert!(p.closed().is_err());
+
+        p.open(Side::Buy).unwrap();
+        assert_eq!(p, PositionState::Long);
+        // 二重エントリー
+        assert!(p.open(Side::Buy).is_err());
+        assert!(p.open(Side::Sell).is_err());
+        // 逆方向の決済
+        assert!(p.exit(Some(Side::Sell)).is_err());
+        assert_eq!(p, PositionState::Long);
+
+        assert_eq!(p.exit(None).unwrap(), Side::Buy);
+        assert_eq!(p, PositionState::Exiting(Side::Buy));
+        assert!(p.exit(None).is_err());
+        assert!(p.open(Side::Sell).is_err());
+        p.closed().unwrap();
+        assert_eq!(p, PositionState::Flat);
+    }
+
+    #[test]
+    fn test_position_check() {
+        let p = PositionState::Short;
+        assert!(p.check(Intent::Exit(Some(Side::Sell))).is_ok());
+        assert!(p.check(Intent::Exit(Some(Side::Buy))).is_err());
+        assert!(p.check(Intent::EntryExit(Side::Buy)).is_err());
+        assert_eq!(p, PositionState::Short);
+        assert!(PositionState::Flat.check(Intent::Entry(Side::Sell)).is_ok());
+    }
+
+    #[test]
+    fn test_position_shared_state() {
+        let s = Arc::new(RwLock::new(Data::default()));
+        open(&s, Side::Sell).unwrap();
+        let e = open(&s, Side::Sell).unwrap_err();
+        assert!(matches!(e, SignalError::InvalidTransition { .. }));
+        assert_eq!(s.read().unwrap().status.message, e.to_string());
+
+        assert_eq!(exit(&s, None).unwrap(), Side::Sell);
+        closed(&s).unwrap();
+        assert_eq!(s.read().unwrap().status.position, PositionState::Flat);
+    }
+
+    #[test]
+    fn test_position_volume() {
+        let s = Arc::new(RwLock::new(Data::default()));
+        assert!(reduce(&s, None, 1).is_err());
+        open_volume(&s, Side::Buy, 3).unwrap();
+        stops::watch(s.clone(), "USDJPY", Side::Buy, rust_decimal::Decimal::ONE, 60_000);
+        assert_eq!(reduce(&s, Some(Side::Buy), 2).unwrap(), Side::Buy);
+        assert_eq!(s.read().unwrap().status.volume, 1);
+        // 決済待ちの数量も減らす
+        assert_eq!(s.read().unwrap().status.watch.as_ref().unwrap().volume, 1);
+        // 残り全数量・逆方向は一部決済では扱わない
+        assert!(reduce(&s, None, 1).is_err());
+        assert!(reduce(&s, Some(Side::Sell), 1).is_err());
+        assert_eq!(s.read().unwrap().status.position, PositionState::Long);
+
+        exit(&s, None).unwrap();
+        closed(&s).unwrap();
+        assert_eq!(s.read().unwrap().status.volume, 0);
+    }
+}
@@ -26,6 +26,18 @@ pub fn unlock(s: Arc<RwLock<invoke::gui::Data>>, order: Option<Order>) {
     }
 }
 
+// 処理中フラグを変えずに注文を記録する
+pub fn record(s: Arc<RwLock<invoke::gui::Data>>, order: Order) {
+    match s.write() {
+        Ok(mut rw) => {
+            rw.status.message = format!("{:?}", order);
+            rw.status.push(order);
+            rw.status.shrink(crate::consts::DEFAULT_ORDER_HISTORY_LIMIT);
+        }
+        Err(e) => log::error!("Lock poisoned in record(): {:?}", e),
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -0,0 +1,157 @@
+use std::{
+    sync::{
+        mpsc::{self, RecvTimeoutError, Sender},
+        Arc, Mutex, RwLock,
+    },
+    time::Duration,
+};
+
+use chrono::Utc;
+use log::{trace, warn};
+
+use crate::{
+    consts::DEFAULT_EXIT_POLL_MS,
+    invoke::gui::Data,
+    middleware::ticker::Ticker,
+    order_type::stops::{self, ExitReason, Watch},
+};
+
+struct ExitTask {
+    setting: Arc<RwLock<Data>>,
+    watch: Watch,
+    reason: ExitReason,
+}
+
+/// 決済待ちの建玉の決済を行う専用スレッド
+/// tick毎の判定はon_tick、tickが届かない間の最大保有時間はスレッドのタイマーで判定する
+/// 決済のマウス操作もこのスレッドで行うため、注文判定のワーカーは待機しない
+pub struct ExitScheduler {
+    tx: Sender<ExitTask>,
+    // タイマーで判定する共有状態
+    watched: Arc<Mutex<Option<Arc<RwLock<Data>>>>>,
+}
+
+impl Default for ExitScheduler {
+    fn default() -> Self {
+        ExitScheduler::new()
+    }
+}
+
+impl ExitScheduler {
+    pub fn new() -> Self {
+        let (tx, rx) = mpsc::channel::<ExitTask>();
+        let watched: Arc<Mutex<Option<Arc<RwLock<Data>>>>> = Arc::new(Mutex::new(None));
+
+        let timer = watched.clone();
+        std::thread::spawn(move || loop {
+            match rx.recv_timeout(Duration::from_millis(DEFAULT_EXIT_POLL_MS)) {
+                Ok(task) => stops::close(task.setting, task.watch, task.reason),
+                Err(RecvTimeoutError::Timeout) => {
+                    let setting = timer.lock().ok().and_then(|w| w.clone());
+                    let Some(setting) = setting else {
+                        continue;
+                    };
+                    if let Some((watch, reason)) = stops::check(&setting, None, Utc::now()) {
+                        stops::close(setting, watch, reason);
+                    }
+                }
+                Err(RecvTimeoutError::Disconnected) => break,
+            }
+        });
+
+        ExitScheduler { tx, watched }
+    }
+
+    // CORE: 受信tickで決済待ちの建玉を判定し、決済する場合はスレッドへ送る
+    pub fn on_tick(&self, logic_setting: &Arc<RwLock<Data>>, tick: Option<&Ticker>) -> Option<ExitReason> {
+        if let Ok(mut watched) = self.watched.lock() {
+            if !watched.as_ref().is_some_and(|w| Arc::ptr_eq(w, logic_setting)) {
+                *watched = Some(logic_setting.clone());
+            }
+        }
+
+        let (watch, reason) = stops::check(logic_setting, tick, Utc::now())?;
+        trace!("schedule exit {} by {:?}", watch.side.as_str(), reason);
+        let task = ExitTask {
+            setting: logic_setting.clone(),
+            watch,
+            reason,
+        };
+        if let Err(e) = self.tx.send(task) {
+            // スレッドが停止している場合は監視に戻す
+            warn!("exit scheduler stopped: {}", e);
+            if let Ok(mut rw) = logic_setting.write() {
+                rw.status.watch = Some(e.0.watch);
+            }
+            return None;
+        }
+        Some(reason)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::order_type::{position, process, signal::Side};
+    use rust_decimal::Decimal;
+
+    fn tick(price: i64) -> Ticker {
+        Ticker {
+            symbol: "USDJPY".to_string(),
+            bid: Decimal::new(price, 3),
+            ask: Decimal::new(price, 3),
+            ..Default::default()
+        }
+    }
+
+    fn wait_flat(setting: &Arc<RwLock<Data>>) {
+        for _ in 0..100 {
+            if setting.read().unwrap().status.watch.is_none() && setting.read().unwrap().status.position == position::PositionState::Flat {
+                return;
+            }
+            std::thread::sleep(Duration::from_millis(10));
+        }
+    }
+
+    fn watched(side: Side, max_ms: u64) -> Arc<RwLock<Data>> {
+        let mut data = Data::default();
+        data.mouse_exit.n = 0;
+        let setting = Arc::new(RwLock::new(data));
+        position::open(&setting, side).unwrap();
+        process::lock(setting.clone());
+        stops::watch(setting.clone(), "USDJPY", side, Decimal::new(150_000, 3), max_ms);
+        setting
+    }
+
+    #[test]
+    fn test_scheduler_max_hold_without_ticks() {
+        let scheduler = ExitScheduler::new();
+        let setting = watched(Side::Buy, 50);
+        // 登録後は処理中フラグを下げ、注文判定を続ける
+        assert!(!setting.read().unwrap().status.is_processing);
+
+        assert_eq!(scheduler.on_tick(&setting, Some(&tick(150_010))), None);
+        wait_flat(&setting);
+        let read = setting.read().unwrap();
+        assert!(read.status.watch.is_none());
+        assert_eq!(read.status.orders.len(), 1);
+    }
+
+    #[test]
+    fn test_scheduler_opposite_signal() {
+        // Cancel: 予定の決済を取り消して即時決済する
+        let scheduler = ExitScheduler::new();
+        let setting = watched(Side::Buy, 60_000);
+        setting.write().unwrap().setting.opposite = crate::invoke::gui::OppositeSignal::Cancel;
+        assert!(position::open(&setting, Side::Sell).is_err());
+        assert_eq!(scheduler.on_tick(&setting, Some(&tick(149_990))), Some(ExitReason::Opposite));
+        wait_flat(&setting);
+        assert_eq!(setting.read().unwrap().status.position, position::PositionState::Flat);
+
+        // Ignore: 同方向・逆方向とも予定通り
+        let setting = watched(Side::Buy, 60_000);
+        assert!(position::open(&setting, Side::Sell).is_err());
+        assert_eq!(scheduler.on_tick(&setting, Some(&tick(149_990))), None);
+        assert_eq!(setting.read().unwrap().status.position, position::PositionState::Long);
+    }
+}
@@ -0,0 +1,14 @@
+use serde::{Deserialize, Serialize};
+
+pub use crate::middleware::command::Side;
+
+/// 注文判定の結果、マウス操作やシミュレーションはこれを受けて実行する
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum Intent {
+    // 新規注文のみ
+    Entry(Side),
+    // 新規注文後、待機を経て決済する
+    EntryExit(Side),
+    // 決済のみ、Noneは方向を問わない
+    Exit(Option<Side>),
+}
@@ -1,19 +1,22 @@
 use std::sync::{Arc, RwLock};
 
 use crate::{
-    invoke::{self, gui::Order},
-    middleware::{mouse, ticker::TickerStats, utils},
-    order_type::process,
+    invoke::{self, gui::Setting},
+    middleware::{mouse, ticker::TickerStats},
+    order_type::{
+        position, process,
+        signal::{Intent, Side},
+        stops,
+    },
 };
 
 use log::{trace, warn};
-use rust_decimal::{prelude::Zero, Decimal};
 
 /// シンプルな注文及び決済処理を行う
 /// 指定時間遡り、直近のTicker mid値と現在のTicker mid値の差分を計算し、設定値以上差が生じれば注文を行う
-/// 指定時間待機し、決済注文を行う
+/// 決済は建玉の監視に登録し、利確・損切り・トレーリングに達するか指定時間経過後に行う
 pub fn process(logic_setting: Arc<RwLock<invoke::gui::Data>>, tickers: &TickerStats) {
-    let (setting, buy_mouse, sell_mouse, exit_mouse) = {
+    let (setting, buy_mouse, sell_mouse) = {
         let readed = match logic_setting.read() {
             Ok(setting) => setting,
             Err(e) => {
@@ -26,10 +29,38 @@ pub fn process(logic_setting: Arc<RwLock<invoke::gui::Data>>, tickers: &TickerSt
             readed.setting.clone(),
             readed.mouse_entry_buy.clone(),
             readed.mouse_entry_sell.clone(),
-            readed.mouse_exit.clone(),
         )
     };
 
+    let Some(Intent::EntryExit(side)) = signal(&setting, tickers) else {
+        return;
+    };
+    if position::open(&logic_setting, side).is_err() {
+        return;
+    }
+
+    // 処理中フラグを立てる
+    process::lock(logic_setting.clone());
+
+    let last = tickers.last().unwrap();
+    let entry_price = side.entry_price(last);
+    let entry_mouse = match side {
+        Side::Buy => buy_mouse,
+        Side::Sell => sell_mouse,
+    };
+
+    // 新規注文のマウス操作
+    let mouse_c = mouse::Mouse::default();
+    mouse_c.order(&entry_mouse);
+
+    // 建玉を監視し、決済はExitSchedulerが行う
+    let target_sleep_ms = setting.get_sleep_ms();
+    stops::watch(logic_setting, &last.symbol, side, entry_price, target_sleep_ms);
+}
+
+/// 注文判定のみを行う、バックテストと共通
+/// 指定時間遡った価格との差分が設定値を超えれば、差分の方向へエントリーし待機後に決済する
+pub fn signal(setting: &Setting, tickers: &TickerStats) -> Option<Intent> {
     // 設定条件を取得
     let (target_diff_micros, target_diff_ticks) = setting.get();
     trace!(
@@ -38,45 +69,22 @@ pub fn process(logic_setting: Arc<RwLock<invoke::gui::Data>>, tickers: &TickerSt
         target_diff_ticks,
     );
 
-    let diff = tickers.diff(target_diff_micros);
-
-    if target_diff_ticks < diff.abs() {
-        // 処理中フラグを立てる
-        process::lock(logic_setting.clone());
-
-        let entry_price = tickers.last().unwrap().mid();
-        let mut order = Order::new(entry_price);
-
-        let entry_mouse = {
-            // 0値は上記条件で弾かれるため内包する
-            // 0 < diff = buy, 0 > diff = sell
-            if diff > Decimal::zero() {
-                order.side = "buy".to_string();
-                buy_mouse
-            } else {
-                order.side = "sell".to_string();
-                sell_mouse
-            }
-        };
-
-        // 新規注文のマウス操作
-        let mouse_c = mouse::Mouse::default();
-        mouse_c.order(&entry_mouse);
-
-        // 設定値待機する
-        let target_sleep_ms = setting.get_sleep_ms();
-        utils::sleep(0, target_sleep_ms);
-
-        // 決済注文のマウス操作
-        let n = exit_mouse.n;
-        for _ in 0..n {
-            mouse_c.order(&exit_mouse);
-            utils::sleep(1, 0);
+    let diff = match tickers.diff(target_diff_micros) {
+        Ok(diff) => diff,
+        Err(e) => {
+            trace!("skip: {}", e);
+            return None;
         }
+    };
+    if target_diff_ticks >= diff.abs() {
+        return None;
+    }
 
-        order.done(None);
-
-        // フラグを下げる
-        process::unlock(logic_setting.clone(), Some(order));
+    if !setting.spread_gate().allows_entry(tickers) {
+        return None;
     }
+
+    // 0値は上記条件で弾かれるため内包する
+    // 0 < diff = buy, 0 > diff = sell
+    Side::from_diff(diff).map(Intent::EntryExit)
 }
@@ -0,0 +1,504 @@
+use std::sync::{Arc, RwLock};
+
+use chrono::{DateTime, Utc};
+use log::{info, trace, warn};
+use rust_decimal::Decimal;
+use serde::{Deserialize, Serialize};
+
+use crate::{
+    invoke::gui::{Data, Mouse, OppositeSignal, Order},
+    middleware::{mouse, ticker::Ticker, utils},
+    order_type::{position, process, signal::Side},
+};
+
+/// 価格による決済条件、いずれも建値からの価格差でNoneは無効
+/// trailing_stop: 建玉以降の最も有利な価格からの戻り幅
+/// break_even: この利益幅に達した後は逆指値を建値へ移動する
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize, Serialize)]
+pub struct ExitRule {
+    pub take_profit: Option<Decimal>,
+    pub stop_loss: Option<Decimal>,
+    pub trailing_stop: Option<Decimal>,
+    pub break_even: Option<Decimal>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ExitReason {
+    TakeProfit,
+    StopLoss,
+    TrailingStop,
+    BreakEven,
+    // 最大保有時間の経過
+    MaxHold,
+    // 逆方向のシグナル
+    Opposite,
+}
+
+/// 建玉1つ分の決済監視
+/// tick毎にupdateで最も有利な価格と逆指値を更新し、決済条件を満たせば理由を返す
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Watch {
+    // 建玉のシンボル、他シンボルの価格では判定しない
+    #[serde(default)]
+    pub symbol: String,
+    pub side: Side,
+    // 建玉の数量、決済時はこの数量分クリックする
+    #[serde(default = "default_volume")]
+    pub volume: u32,
+    pub entry: Decimal,
+    pub entried_at: DateTime<Utc>,
+    // 最大保有時間の期限
+    pub deadline: DateTime<Utc>,
+    pub rule: ExitRule,
+    // 建玉以降の最も有利な価格
+    pub best: Decimal,
+    // 現在の逆指値と、到達した場合の決済理由
+    pub stop: Option<(Decimal, ExitReason)>,
+    // 直近の価格
+    pub last: Decimal,
+    // 逆方向のシグナルによる即時決済の要求と、決済後に新規する方向
+    #[serde(default)]
+    pub requested: Option<ExitReason>,
+    #[serde(default)]
+    pub reverse: Option<Side>,
+}
+
+fn default_volume() -> u32 {
+    1
+}
+
+impl Watch {
+    pub fn new(
+        symbol: &str,
+        side: Side,
+        entry: Decimal,
+        entried_at: DateTime<Utc>,
+        max_hold: chrono::Duration,
+        rule: ExitRule,
+    ) -> Self {
+        let stop = rule.stop_loss.map(|sl| (entry - sl * side.sign(), ExitReason::StopLoss));
+        Watch {
+            symbol: symbol.to_string(),
+            side,
+            volume: default_volume(),
+            entry,
+            entried_at,
+            deadline: entried_at + max_hold,
+            rule,
+            best: entry,
+            stop,
+            last: entry,
+            requested: None,
+            reverse: None,
+        }
+    }
+
+    // 逆方向のシグナルを設定に従い反映する、予定の決済を変更した場合はtrue
+    pub fn request(&mut self, side: Side, policy: OppositeSignal) -> bool {
+        if side == self.side || self.requested.is_some() {
+            return false;
+        }
+        match policy {
+            OppositeSignal::Ignore => return false,
+            OppositeSignal::Cancel => {}
+            OppositeSignal::Replace => self.reverse = Some(side),
+        }
+        self.requested = Some(ExitReason::Opposite);
+        true
+    }
+
+    pub fn is_expired(&self, now: DateTime<Utc>) -> bool {
+        now >= self.deadline
+    }
+
+    // 建値に対する損益幅
+    fn profit(&self, price: Decimal) -> Decimal {
+        (price - self.entry) * self.side.sign()
+    }
+
+    // 逆指値を建玉に有利な方向へのみ動かす
+    fn raise_stop(&mut self, price: Decimal, reason: ExitReason) {
+        let tighter = match self.stop {
+            None => true,
+            Some((stop, _)) => (price - stop) * self.side.sign() > Decimal::ZERO,
+        };
+        if tighter {
+            self.stop = Some((price, reason));
+        }
+    }
+
+    // CORE: 価格を反映し、即時決済の要求・逆指値・利確・最大保有時間の順に判定する
+    pub fn update(&mut self, price: Decimal, now: DateTime<Utc>) -> Option<ExitReason> {
+        self.last = price;
+        if self.requested.is_some() {
+            return self.requested;
+        }
+        if self.profit(price) > self.profit(self.best) {
+            self.best = price;
+        }
+        let best_profit = self.profit(self.best);
+        if self.rule.break_even.is_some_and(|be| best_profit >= be) {
+            self.raise_stop(self.entry, ExitReason::BreakEven);
+        }
+        if let Some(trail) = self.rule.trailing_stop {
+            self.raise_stop(self.best - trail * self.side.sign(), ExitReason::TrailingStop);
+        }
+
+        if let Some((stop, reason)) = self.stop {
+            if (price - stop) * self.side.sign() <= Decimal::ZERO {
+                return Some(reason);
+            }
+        }
+        if self.rule.take_profit.is_some_and(|tp| self.profit(price) >= tp) {
+            return Some(ExitReason::TakeProfit);
+        }
+        self.is_expired(now).then_some(ExitReason::MaxHold)
+    }
+}
+
+/// 新規注文後に決済監視を登録し、処理中フラグを下げる
+/// 数量は新規時に登録した建玉の数量とし、決済はExitSchedulerがtick・タイマーで判定し、待機中も注文判定を続ける
+pub fn watch(logic_setting: Arc<RwLock<Data>>, symbol: &str, side: Side, entry: Decimal, max_ms: u64) {
+    match logic_setting.write() {
+        Ok(mut rw) => {
+            let rule = rw.setting.exit_rule();
+            let mut w = Watch::new(symbol, side, entry, Utc::now(), chrono::Duration::milliseconds(max_ms as i64), rule);
+            w.volume = rw.status.volume.max(1);
+            info!(
+                "watch {} {} x{} entry {}, until {}",
+                symbol,
+                side.as_str(),
+                w.volume,
+                entry,
+                w.deadline.format("%H:%M:%S")
+            );
+            rw.status.watch = Some(w);
+            rw.status.processed();
+        }
+        Err(e) => log::error!("Lock poisoned in watch(): {:?}", e),
+    }
+}
+
+/// 決済待ちの建玉と逆方向のシグナルを設定に従い反映する
+pub fn opposite(logic_setting: &Arc<RwLock<Data>>, side: Side) -> bool {
+    let Ok(mut rw) = logic_setting.write() else {
+        return false;
+    };
+    let policy = rw.setting.opposite;
+    let changed = rw.status.watch.as_mut().is_some_and(|w| w.request(side, policy));
+    if changed {
+        info!("opposite {} signal, {:?} pending exit", side.as_str(), policy);
+    }
+    changed
+}
+
+/// 監視中の建玉をtick・時刻で判定し、決済する場合は監視を外して返す
+/// バックテストと同じく決済約定価格 (買いはbid、売りはask) で判定する
+/// tickがNone、または建玉と異なるシンボルの場合は最大保有時間のみ判定する
+pub fn check(logic_setting: &Arc<RwLock<Data>>, tick: Option<&Ticker>, now: DateTime<Utc>) -> Option<(Watch, ExitReason)> {
+    // ポーリング毎に呼ばれるため、監視がなければ書き込みロックを取らない
+    match logic_setting.read() {
+        Ok(read) if read.status.watch.is_none() => return None,
+        Ok(_) => {}
+        Err(e) => {
+            warn!("failed to read setting: {:?}", e);
+            return None;
+        }
+    }
+    let mut rw = match logic_setting.write() {
+        Ok(rw) => rw,
+        Err(e) => {
+            warn!("failed to write setting: {:?}", e);
+            return None;
+        }
+    };
+    let w = rw.status.watch.as_mut()?;
+    let reason = match tick.filter(|t| t.symbol == w.symbol) {
+        Some(t) => w.update(w.side.exit_price(t), now),
+        None => w.requested.or(w.is_expired(now).then_some(ExitReason::MaxHold)),
+    }?;
+    rw.status.watch.take().map(|w| (w, reason))
+}
+
+/// 監視中の数量分の決済注文のマウス操作を行い、注文を記録する
+/// 逆方向への置き換えが要求されていれば続けて同じ数量で新規注文を行う
+// checkで取り出した監視を戻す、決済理由は要求として保持する
+fn restore(logic_setting: &Arc<RwLock<Data>>, mut w: Watch, reason: ExitReason) {
+    match logic_setting.write() {
+        Ok(mut rw) if rw.status.watch.is_none() => {
+            w.requested = Some(reason);
+            rw.status.watch = Some(w);
+        }
+        Ok(_) => trace!("watch is already replaced"),
+        Err(e) => warn!("failed to write setting: {:?}", e),
+    }
+}
+
+pub fn close(logic_setting: Arc<RwLock<Data>>, w: Watch, reason: ExitReason) {
+    let exit_mouse = match logic_setting.read() {
+        Ok(read) => read.mouse_exit.clone(),
+        Err(e) => {
+            warn!("failed to read setting: {:?}", e);
+            return;
+        }
+    };
+    info!(
+        "exit {} x{} by {:?}, entry {}, best {}",
+        w.side.as_str(),
+        w.volume,
+        reason,
+        w.entry,
+        w.best
+    );
+    if let Err(e) = position::exit(&logic_setting, Some(w.side)) {
+        // 決済できない間は監視を戻し、次のtick・ポーリングで再度決済する
+        warn!("failed to exit, keep watching: {}", e);
+        restore(&logic_setting, w, reason);
+        return;
+    }
+    click_exit(&exit_mouse, w.volume.max(1));
+
+    let mut order = Order::new(w.entry);
+    order.side = w.side.as_str().to_string();
+    order.entried_at = w.entried_at;
+    // 決済価格は最後に判定した決済約定価格 (買いはbid、売りはask)
+    order.done(Some(w.last));
+    let _ = position::closed(&logic_setting);
+    process::record(logic_setting.clone(), order);
+
+    if let Some(side) = w.reverse {
+        enter(logic_setting, &w.symbol, side, w.volume.max(1), w.last);
+    }
+}
+
+/// 数量分クリックする、連続する場合は1秒空ける
+/// クリック回数nが0のボタンは操作しない
+pub fn click(mouse_setting: &Mouse, volume: u32) {
+    if mouse_setting.n == 0 {
+        return;
+    }
+    let mouse_c = mouse::Mouse::default();
+    for i in 0..volume {
+        if i > 0 {
+            utils::sleep(1, 0);
+        }
+        mouse_c.order(mouse_setting);
+    }
+}
+
+/// 決済ボタンを数量分クリックする、確認ダイアログに備えて1数量毎に設定の回数クリックする
+pub fn click_exit(exit_mouse: &Mouse, volume: u32) {
+    click(exit_mouse, volume * exit_mouse.n as u32);
+}
+
+// 新規注文のマウス操作を行い、決済監視を登録する
+fn enter(logic_setting: Arc<RwLock<Data>>, symbol: &str, side: Side, volume: u32, entry: Decimal) {
+    let (entry_mouse, max_ms) = match logic_setting.read() {
+        Ok(read) => {
+            let mouse = match side {
+                Side::Buy => read.mouse_entry_buy.clone(),
+                Side::Sell => read.mouse_entry_sell.clone(),
+            };
+            (mouse, read.setting.get_sleep_ms())
+        }
+        Err(e) => {
+            warn!("failed to read setting: {:?}", e);
+            return;
+        }
+    };
+    if position::open_volume(&logic_setting, side, volume).is_err() {
+        return;
+    }
+    info!("reverse entry {} at {}", side.as_str(), entry);
+
+    process::lock(logic_setting.clone());
+    click(&entry_mouse, volume);
+    watch(logic_setting, symbol, side, entry, max_ms);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use chrono::TimeZone;
+    use std::str::FromStr;
+
+    fn d(s: &str) -> Decimal {
+        Decimal::from_str(s).unwrap()
+    }
+
+    fn at(ms: i64) -> DateTime<Utc> {
+        Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap() + chrono::Duration::milliseconds(ms)
+    }
+
+    fn watch_of(side: Side, rule: ExitRule) -> Watch {
+        Watch::new("USDJPY", side, d("150.00"), at(0), chrono::Duration::seconds(10), rule)
+    }
+
+    #[test]
+    fn test_watch_take_profit_and_stop_loss() {
+        let rule = ExitRule {
+            take_profit: Some(d("0.05")),
+            stop_loss: Some(d("0.03")),
+            ..Default::default()
+        };
+        assert_eq!(watch_of(Side::Buy, rule).update(d("150.05"), at(1)), Some(ExitReason::TakeProfit));
+        assert_eq!(watch_of(Side::Buy, rule).update(d("149.97"), at(1)), Some(ExitReason::StopLoss));
+        assert_eq!(watch_of(Side::Buy, rule).update(d("150.02"), at(1)), None);
+        assert_eq!(watch_of(Side::Sell, rule).update(d("149.95"), at(1)), Some(ExitReason::TakeProfit));
+        assert_eq!(watch_of(Side::Sell, rule).update(d("150.03"), at(1)), Some(ExitReason::StopLoss));
+
+        // 条件なしは最大保有時間のみ
+        let mut w = watch_of(Side::Buy, ExitRule::default());
+        assert_eq!(w.update(d("100"), at(9_999)), None);
+        assert_eq!(w.update(d("100"), at(10_000)), Some(ExitReason::MaxHold));
+    }
+
+    #[test]
+    fn test_watch_trailing_stop() {
+        let rule = ExitRule {
+            trailing_stop: Some(d("0.03")),
+            ..Default::default()
+        };
+        let mut w = watch_of(Side::Buy, rule);
+        assert_eq!(w.update(d("150.05"), at(1)), None);
+        assert_eq!(w.update(d("150.10"), at(2)), None);
+        assert_eq!(w.stop, Some((d("150.07"), ExitReason::TrailingStop)));
+        // 戻っても逆指値は下げない
+        assert_eq!(w.update(d("150.08"), at(3)), None);
+        assert_eq!(w.best, d("150.10"));
+        assert_eq!(w.update(d("150.07"), at(4)), Some(ExitReason::TrailingStop));
+
+        let mut w = watch_of(Side::Sell, rule);
+        assert_eq!(w.update(d("149.90"), at(1)), None);
+        assert_eq!(w.update(d("149.93"), at(2)), Some(ExitReason::TrailingStop));
+    }
+
+    #[test]
+    fn test_watch_break_even() {
+        let rule = ExitRule {
+            stop_loss: Some(d("0.05")),
+            break_even: Some(d("0.03")),
+            ..Default::default()
+        };
+        let mut w = watch_of(Side::Buy, rule);
+        assert_eq!(w.update(d("150.02"), at(1)), None);
+        assert_eq!(w.stop, Some((d("149.95"), ExitReason::StopLoss)));
+        assert_eq!(w.update(d("150.03"), at(2)), None);
+        assert_eq!(w.stop, Some((d("150.00"), ExitReason::BreakEven)));
+        assert_eq!(w.update(d("150.00"), at(3)), Some(ExitReason::BreakEven));
+    }
+
+    #[test]
+    fn test_watch_opposite_request() {
+        let mut w = watch_of(Side::Buy, ExitRule::default());
+        assert!(!w.request(Side::Sell, OppositeSignal::Ignore));
+        assert!(!w.request(Side::Buy, OppositeSignal::Cancel));
+        assert_eq!(w.update(d("150.01"), at(1)), None);
+
+        assert!(w.request(Side::Sell, OppositeSignal::Cancel));
+        assert_eq!(w.reverse, None);
+        // 要求済みは上書きしない
+        assert!(!w.request(Side::Sell, OppositeSignal::Replace));
+        assert_eq!(w.update(d("149.99"), at(2)), Some(ExitReason::Opposite));
+        assert_eq!(w.last, d("149.99"));
+
+        let mut w = watch_of(Side::Sell, ExitRule::default());
+        assert!(w.request(Side::Buy, OppositeSignal::Replace));
+        assert_eq!(w.reverse, Some(Side::Buy));
+    }
+
+    #[test]
+    fn test_check_takes_watch() {
+        let mut data = Data::default();
+        data.setting.take_profit = "5".to_string();
+        let s = Arc::new(RwLock::new(data));
+        watch(s.clone(), "USDJPY", Side::Buy, d("150.00"), 10_000);
+
+        let tick = |bid: &str, ask: &str| Ticker {
+            symbol: "USDJPY".to_string(),
+            bid: d(bid),
+            ask: d(ask),
+            ..Default::default()
+        };
+        let now = Utc::now();
+        assert!(check(&s, Some(&tick("150.01", "150.06")), now).is_none());
+        assert!(check(&s, None, now).is_none());
+        // 買いはbidで判定する
+        let (w, reason) = check(&s, Some(&tick("150.05", "150.07")), now).unwrap();
+        assert_eq!((w.side, reason), (Side::Buy, ExitReason::TakeProfit));
+        assert!(s.read().unwrap().status.watch.is_none());
+
+        // 他シンボルの価格では判定しない
+        watch(s.clone(), "USDJPY", Side::Buy, d("150.00"), 10_000);
+        let other = Ticker {
+            symbol: "EURUSD".to_string(),
+            ..tick("100.00", "100.00")
+        };
+        assert!(check(&s, Some(&other), now).is_none());
+        assert!(s.read().unwrap().status.watch.is_some());
+        assert!(check(&s, Some(&tick("149.00", "149.01")), now).is_none());
+        s.write().unwrap().status.watch = None;
+
+        // 期限切れはtickがなくても決済する
+        watch(s.clone(), "USDJPY", Side::Sell, d("150.00"), 0);
+        assert_eq!(check(&s, None, Utc::now()).map(|(_, r)| r), Some(ExitReason::MaxHold));
+    }
+
+    #[test]
+    fn test_check_without_watch_reads_only() {
+        let s = Arc::new(RwLock::new(Data::default()));
+        // 監視がなければ書き込みロックを取らないため、読み込み中でも戻る
+        let read = s.read().unwrap();
+        assert!(check(&s, None, Utc::now()).is_none());
+        drop(read);
+    }
+
+    #[test]
+    fn test_close_records_exit_price() {
+        let mut data = Data::default();
+        data.setting.take_profit = "5".to_string();
+        data.mouse_exit.n = 0;
+        let s = Arc::new(RwLock::new(data));
+        position::open(&s, Side::Sell).unwrap();
+        watch(s.clone(), "USDJPY", Side::Sell, d("150.00"), 10_000);
+        let tick = Ticker {
+            symbol: "USDJPY".to_string(),
+            bid: d("149.93"),
+            ask: d("149.95"),
+            ..Default::default()
+        };
+
+        // 売りはaskで決済した価格を記録する
+        let (w, reason) = check(&s, Some(&tick), Utc::now()).unwrap();
+        close(s.clone(), w, reason);
+        let read = s.read().unwrap();
+        let order = read.status.orders.back().unwrap();
+        assert_eq!((order.side.as_str(), order.entry, order.exit), ("sell", d("150.00"), d("149.95")));
+    }
+
+    #[test]
+    fn test_close_keeps_watch_on_refused_exit() {
+        let mut data = Data::default();
+        data.setting.take_profit = "5".to_string();
+        data.mouse_exit.n = 0;
+        let s = Arc::new(RwLock::new(data));
+        watch(s.clone(), "USDJPY", Side::Buy, d("150.00"), 10_000);
+        let tick = Ticker {
+            symbol: "USDJPY".to_string(),
+            bid: d("150.05"),
+            ask: d("150.07"),
+            ..Default::default()
+        };
+
+        // 建玉がない状態では決済を拒否され、監視を戻す
+        let (w, reason) = check(&s, Some(&tick), Utc::now()).unwrap();
+        close(s.clone(), w, reason);
+        let read = s.read().unwrap();
+        assert!(read.status.orders.is_empty());
+        assert_eq!(read.status.watch.as_ref().map(|w| w.requested), Some(Some(ExitReason::TakeProfit)));
+        drop(read);
+
+        // 次のポーリングで再度決済する
+        assert_eq!(check(&s, None, Utc::now()).map(|(_, r)| r), Some(ExitReason::TakeProfit));
+    }
+}
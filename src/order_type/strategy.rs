@@ -0,0 +1,423 @@
+use std::{
+    collections::BTreeMap,
+    sync::{Arc, RwLock},
+};
+
+use log::{info, warn};
+
+use crate::{
+    invoke::gui::{Data, Order, OrderType, Setting},
+    middleware::{
+        indicator::IndicatorValue,
+        mouse,
+        ticker::{BarInterval, Retention, Ticker, TickerStats},
+        utils,
+    },
+    order_type::{
+        breakout, entry, exit,
+        origin::{self, Command},
+        position, process,
+        signal::{Intent, Side},
+        simple, stops, zscore,
+    },
+};
+
+/// 注文判定に渡す情報
+#[derive(Debug, Clone, Copy)]
+pub struct Context<'a> {
+    pub setting: &'a Setting,
+    pub tickers: &'a TickerStats,
+}
+
+impl<'a> Context<'a> {
+    pub fn new(setting: &'a Setting, tickers: &'a TickerStats) -> Self {
+        Context { setting, tickers }
+    }
+
+    // 判定対象の最新tick
+    pub fn last(&self) -> Option<&'a Ticker> {
+        self.tickers.last()
+    }
+
+    // 登録済みの指標値、未登録・入力数が足りない場合はNone
+    pub fn indicator(&self, name: &str) -> Option<IndicatorValue> {
+        self.tickers.indicator(name)
+    }
+}
+
+/// 注文ロジック
+/// signalは判定のみを行い、稼働時・バックテストの両方で使用する
+/// processは稼働時の処理で、既定ではIntentに応じた共通のマウス操作を行う
+pub trait Strategy: Send + Sync {
+    fn name(&self) -> &str;
+
+    fn signal(&self, ctx: &Context) -> Option<Intent>;
+
+    // 判定に必要な足の集計 (足の区切り, 保持数)、SymbolBook::add_bars で登録する
+    fn bar_intervals(&self, _setting: &Setting) -> Vec<(BarInterval, usize)> {
+        vec![]
+    }
+
+    // 判定に必要な保持方針、SymbolBookの保持方針をこれを満たすよう広げる
+    fn retention(&self, _setting: &Setting) -> Option<Retention> {
+        None
+    }
+
+    // 数量・保有時間を含む注文指示、バックテストではsignalより優先する
+    fn command(&self, _ctx: &Context) -> Option<Command> {
+        None
+    }
+
+    fn process(&self, logic_setting: Arc<RwLock<Data>>, tickers: &TickerStats) {
+        let setting = match logic_setting.read() {
+            Ok(read) => read.setting.clone(),
+            Err(e) => {
+                warn!("failed to read setting: {:?}", e);
+                return;
+            }
+        };
+
+        if let Some(intent) = self.signal(&Context::new(&setting, tickers)) {
+            info!("{}: {:?}", self.name(), intent);
+            execute(intent, logic_setting, tickers);
+        }
+    }
+}
+
+/// Intentに応じたマウス操作を行う
+/// EntryExitは新規注文後に建玉の監視へ登録し、決済はExitSchedulerが行う
+pub fn execute(intent: Intent, logic_setting: Arc<RwLock<Data>>, tickers: &TickerStats) {
+    let (setting, buy_mouse, sell_mouse, exit_mouse) = {
+        let read = match logic_setting.read() {
+            Ok(read) => read,
+            Err(e) => {
+                warn!("failed to read setting: {:?}", e);
+                return;
+            }
+        };
+        (
+            read.setting.clone(),
+            read.mouse_entry_buy.clone(),
+            read.mouse_entry_sell.clone(),
+            read.mouse_exit.clone(),
+        )
+    };
+
+    // 建玉の状態に合わない注文は行わない
+    let refused = match intent {
+        Intent::Entry(side) | Intent::EntryExit(side) => position::open(&logic_setting, side).err(),
+        Intent::Exit(side) => position::exit(&logic_setting, side).err(),
+    };
+    if refused.is_some() {
+        return;
+    }
+
+    process::lock(logic_setting.clone());
+    let mouse_c = mouse::Mouse::default();
+    let entry_mouse = |side: Side| match side {
+        Side::Buy => buy_mouse.clone(),
+        Side::Sell => sell_mouse.clone(),
+    };
+    let exit = || {
+        for _ in 0..exit_mouse.n {
+            mouse_c.order(&exit_mouse);
+            utils::sleep(1, 0);
+        }
+    };
+
+    let order = match intent {
+        Intent::Entry(side) | Intent::EntryExit(side) => {
+            let (symbol, entry) = tickers
+                .last()
+                .map(|t| (t.symbol.as_str(), side.entry_price(t)))
+                .unwrap_or_default();
+            mouse_c.order(&entry_mouse(side));
+
+            if let Intent::EntryExit(_) = intent {
+                stops::watch(logic_setting, symbol, side, entry, setting.get_sleep_ms());
+                return;
+            }
+            let mut order = Order::new(entry);
+            order.side = side.as_str().to_string();
+            Some(order)
+        }
+        Intent::Exit(_) => {
+            exit();
+            let _ = position::closed(&logic_setting);
+            None
+        }
+    };
+
+    process::unlock(logic_setting, order);
+}
+
+pub struct SimpleStrategy;
+
+impl Strategy for SimpleStrategy {
+    fn name(&self) -> &str {
+        OrderType::Simple.strategy_name()
+    }
+
+    fn signal(&self, ctx: &Context) -> Option<Intent> {
+        simple::signal(ctx.setting, ctx.tickers)
+    }
+
+    fn process(&self, logic_setting: Arc<RwLock<Data>>, tickers: &TickerStats) {
+        simple::process(logic_setting, tickers);
+    }
+}
+
+// BuyEntry/SellEntry
+pub struct EntryStrategy(pub OrderType);
+
+impl Strategy for EntryStrategy {
+    fn name(&self) -> &str {
+        self.0.strategy_name()
+    }
+
+    fn signal(&self, ctx: &Context) -> Option<Intent> {
+        entry::signal(self.0, ctx.setting, ctx.tickers)
+    }
+
+    fn process(&self, logic_setting: Arc<RwLock<Data>>, tickers: &TickerStats) {
+        entry::process(self.0, logic_setting, tickers);
+    }
+}
+
+pub struct ExitStrategy;
+
+impl Strategy for ExitStrategy {
+    fn name(&self) -> &str {
+        OrderType::ExitOnly.strategy_name()
+    }
+
+    fn signal(&self, ctx: &Context) -> Option<Intent> {
+        exit::signal(ctx.setting, ctx.tickers)
+    }
+
+    fn process(&self, logic_setting: Arc<RwLock<Data>>, tickers: &TickerStats) {
+        exit::process(logic_setting, tickers);
+    }
+}
+
+pub struct ZScoreStrategy;
+
+impl Strategy for ZScoreStrategy {
+    fn name(&self) -> &str {
+        OrderType::ZScore.strategy_name()
+    }
+
+    fn signal(&self, ctx: &Context) -> Option<Intent> {
+        zscore::signal(ctx.setting, ctx.tickers)
+    }
+
+    fn process(&self, logic_setting: Arc<RwLock<Data>>, tickers: &TickerStats) {
+        zscore::process(logic_setting, tickers);
+    }
+}
+
+pub struct BreakoutStrategy;
+
+impl Strategy for BreakoutStrategy {
+    fn name(&self) -> &str {
+        OrderType::Breakout.strategy_name()
+    }
+
+    fn signal(&self, ctx: &Context) -> Option<Intent> {
+        breakout::signal(ctx.setting, ctx.tickers)
+    }
+
+    fn bar_intervals(&self, setting: &Setting) -> Vec<(BarInterval, usize)> {
+        breakout::bar_intervals(setting)
+    }
+
+    fn retention(&self, setting: &Setting) -> Option<Retention> {
+        breakout::retention(setting)
+    }
+
+    fn process(&self, logic_setting: Arc<RwLock<Data>>, tickers: &TickerStats) {
+        breakout::process(logic_setting, tickers);
+    }
+}
+
+pub struct OriginStrategy;
+
+impl Strategy for OriginStrategy {
+    fn name(&self) -> &str {
+        OrderType::Custom.strategy_name()
+    }
+
+    fn signal(&self, ctx: &Context) -> Option<Intent> {
+        origin::signal(ctx.tickers)
+    }
+
+    fn command(&self, ctx: &Context) -> Option<Command> {
+        origin::command(ctx.tickers)
+    }
+
+    fn process(&self, logic_setting: Arc<RwLock<Data>>, tickers: &TickerStats) {
+        origin::process(logic_setting, tickers);
+    }
+}
+
+/// 名前付きの注文ロジック一覧
+/// Defaultは組み込みのorder_typeを登録済み、ホストアプリからregisterで追加・置き換えできる
+#[derive(Clone)]
+pub struct StrategyRegistry {
+    strategies: BTreeMap<String, Arc<dyn Strategy>>,
+}
+
+impl Default for StrategyRegistry {
+    fn default() -> Self {
+        let mut registry = StrategyRegistry::new();
+        registry.register(SimpleStrategy);
+        registry.register(EntryStrategy(OrderType::BuyEntry));
+        registry.register(EntryStrategy(OrderType::SellEntry));
+        registry.register(ExitStrategy);
+        registry.register(ZScoreStrategy);
+        registry.register(BreakoutStrategy);
+        registry.register(OriginStrategy);
+        registry
+    }
+}
+
+impl std::fmt::Debug for StrategyRegistry {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_list().entries(self.strategies.keys()).finish()
+    }
+}
+
+impl StrategyRegistry {
+    // 空の一覧
+    pub fn new() -> Self {
+        StrategyRegistry {
+            strategies: BTreeMap::new(),
+        }
+    }
+
+    // 同名の登録があれば置き換え、以前のものを返す
+    pub fn register<S: Strategy + 'static>(&mut self, strategy: S) -> Option<Arc<dyn Strategy>> {
+        self.strategies.insert(strategy.name().to_string(), Arc::new(strategy))
+    }
+
+    pub fn get(&self, name: &str) -> Option<Arc<dyn Strategy>> {
+        self.strategies.get(name).cloned()
+    }
+
+    pub fn names(&self) -> Vec<&str> {
+        self.strategies.keys().map(|k| k.as_str()).collect()
+    }
+
+    // CORE: 設定に対応する注文ロジック
+    // strategyが指定されていればその名前、空ならorder_typeの組み込みを使用する
+    pub fn resolve(&self, setting: &Setting) -> Option<Arc<dyn Strategy>> {
+        self.get(setting.strategy_name())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::indicator::{IndicatorConfig, IndicatorKind, IndicatorSource};
+    use rust_decimal::{
+        prelude::{FromPrimitive, ToPrimitive},
+        Decimal,
+    };
+
+    // 最新tickが直前より上昇していれば買い
+    struct Momentum;
+
+    impl Strategy for Momentum {
+        fn name(&self) -> &str {
+            "momentum"
+        }
+
+        fn signal(&self, ctx: &Context) -> Option<Intent> {
+            let mut recent = ctx.tickers.iter().rev();
+            let (last, prev) = (recent.next()?, recent.next()?);
+            Side::from_diff(last.bid - prev.bid).map(Intent::Entry)
+        }
+    }
+
+    // 最新のmidが単純移動平均より上なら買い、下なら売り
+    struct SmaTrend;
+
+    impl Strategy for SmaTrend {
+        fn name(&self) -> &str {
+            "sma_trend"
+        }
+
+        fn signal(&self, ctx: &Context) -> Option<Intent> {
+            let sma = ctx.indicator("sma")?.value();
+            let mid = ctx.last()?.mid().to_f64()?;
+            Side::from_diff(Decimal::from_f64(mid - sma)?).map(Intent::Entry)
+        }
+    }
+
+    #[test]
+    fn test_strategy_reads_indicator() {
+        let mut tickers = TickerStats::new();
+        tickers
+            .add_indicator(IndicatorConfig::new("sma", IndicatorKind::Sma(3), IndicatorSource::Tick))
+            .unwrap();
+        let setting = Setting::new();
+        let tick = |bid: i64| Ticker {
+            bid: Decimal::new(bid, 3),
+            ask: Decimal::new(bid, 3),
+            ..Default::default()
+        };
+        tickers.push(tick(150_000));
+        tickers.push(tick(150_010));
+        // 入力数が足りない間は判定しない
+        assert_eq!(SmaTrend.signal(&Context::new(&setting, &tickers)), None);
+
+        tickers.push(tick(150_050));
+        assert_eq!(SmaTrend.signal(&Context::new(&setting, &tickers)), Some(Intent::Entry(Side::Buy)));
+    }
+
+    #[test]
+    fn test_registry_builtin() {
+        let registry = StrategyRegistry::default();
+        assert_eq!(registry.names(), vec!["breakout", "buy_entry", "custom", "exit_only", "sell_entry", "simple", "zscore"]);
+
+        let mut setting = Setting::new();
+        setting.order_type = OrderType::SellEntry;
+        assert_eq!(registry.resolve(&setting).unwrap().name(), "sell_entry");
+
+        // 組み込みのsignalは各order_typeの判定と同じ
+        let mut tickers = TickerStats::new();
+        tickers.push(Ticker {
+            flag: Some(3),
+            ..Default::default()
+        });
+        setting.order_type = OrderType::Custom;
+        let ctx = Context::new(&setting, &tickers);
+        assert_eq!(registry.resolve(&setting).unwrap().signal(&ctx), Some(Intent::EntryExit(Side::Buy)));
+    }
+
+    #[test]
+    fn test_registry_custom_strategy() {
+        let mut registry = StrategyRegistry::default();
+        assert!(registry.register(Momentum).is_none());
+        assert!(registry.register(Momentum).is_some());
+
+        let mut setting = Setting::new();
+        setting.strategy = "momentum".to_string();
+        let strategy = registry.resolve(&setting).unwrap();
+
+        let tickers = TickerStats::from(vec![
+            Ticker {
+                bid: Decimal::new(150_000, 3),
+                ..Default::default()
+            },
+            Ticker {
+                bid: Decimal::new(149_990, 3),
+                ..Default::default()
+            },
+        ]);
+        assert_eq!(strategy.signal(&Context::new(&setting, &tickers)), Some(Intent::Entry(Side::Sell)));
+
+        setting.strategy = "unknown".to_string();
+        assert!(registry.resolve(&setting).is_none());
+    }
+}
@@ -0,0 +1,174 @@
+use std::{
+    str::FromStr,
+    sync::{Arc, RwLock},
+};
+
+use log::{info, trace, warn};
+use rust_decimal::Decimal;
+
+use crate::{
+    invoke::{
+        self,
+        gui::{Setting, ZScoreMode},
+    },
+    middleware::ticker::{PriceField, TickerStats},
+    order_type::{
+        signal::{Intent, Side},
+        strategy,
+    },
+};
+
+/// Zスコアの判定条件
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct ZScoreParams {
+    pub field: PriceField,
+    pub entry: Decimal,
+    pub exit: Decimal,
+    pub mode: ZScoreMode,
+}
+
+impl ZScoreParams {
+    // 設定値から判定条件を取得する、不正値・exit >= entryの場合はNone
+    pub fn from_setting(setting: &Setting) -> Option<Self> {
+        let field = match PriceField::from_str(setting.zscore_field.trim()) {
+            Ok(PriceField::Spread) | Err(_) => return None,
+            Ok(field) => field,
+        };
+        let entry = Decimal::from_str(setting.zscore_entry.trim()).ok()?;
+        let exit = Decimal::from_str(setting.zscore_exit.trim()).ok()?;
+        if exit < Decimal::ZERO || exit >= entry {
+            return None;
+        }
+        Some(ZScoreParams {
+            field,
+            entry,
+            exit,
+            mode: setting.zscore_mode,
+        })
+    }
+
+    // 乖離の方向に対する新規の方向
+    fn side(&self, z: Decimal) -> Option<Side> {
+        let side = Side::from_diff(z)?;
+        match self.mode {
+            ZScoreMode::Reversion => Some(side.opposite()),
+            ZScoreMode::Momentum => Some(side),
+        }
+    }
+}
+
+/// Zスコアによる注文処理を行う
+/// 新規・決済ともに判定結果に従い共通のマウス操作を行う
+pub fn process(logic_setting: Arc<RwLock<invoke::gui::Data>>, tickers: &TickerStats) {
+    let setting = match logic_setting.read() {
+        Ok(read) => read.setting.clone(),
+        Err(e) => {
+            warn!("failed to read setting: {:?}", e);
+            return;
+        }
+    };
+
+    if let Some(intent) = signal(&setting, tickers) {
+        info!("zscore: {:?}", intent);
+        strategy::execute(intent, logic_setting, tickers);
+    }
+}
+
+/// 注文判定のみを行う、バックテストと共通
+/// 直前のtickから最新tickにかけてZスコアがentryを外側へ越えればエントリーし、
+/// exitの内側へ戻れば、その乖離で建てた方向の建玉を決済する
+/// 直前のtickのZスコアは最新の統計量で計算するため、保持数が少ないと誤差が大きくなる
+pub fn signal(setting: &Setting, tickers: &TickerStats) -> Option<Intent> {
+    // 不正値は保存時に検証するため、tick毎には警告しない
+    let Some(params) = ZScoreParams::from_setting(setting) else {
+        trace!(
+            "invalid zscore setting: field {}, entry {}, exit {}",
+            setting.zscore_field, setting.zscore_entry, setting.zscore_exit
+        );
+        return None;
+    };
+
+    let mut recent = tickers.iter().rev();
+    let (last, prev) = (recent.next()?, recent.next()?);
+    let stats = tickers.rolling(params.field);
+    let z = stats.zscore(params.field.of(last))?;
+    let prev_z = stats.zscore(params.field.of(prev))?;
+    trace!("zscore {} -> {}", prev_z, z);
+
+    if prev_z.abs() < params.entry && z.abs() >= params.entry {
+        if !setting.spread_gate().allows_entry(tickers) {
+            return None;
+        }
+        return params.side(z).map(Intent::Entry);
+    }
+
+    if prev_z.abs() > params.exit && z.abs() <= params.exit {
+        return params.side(prev_z).map(|side| Intent::Exit(Some(side)));
+    }
+
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::middleware::ticker::Ticker;
+
+    fn tickers(bids: &[i64]) -> TickerStats {
+        TickerStats::from(
+            bids.iter()
+                .map(|b| Ticker {
+                    bid: Decimal::new(150_000 + b, 3),
+                    ask: Decimal::new(150_002 + b, 3),
+                    ..Default::default()
+                })
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    fn setting(mode: ZScoreMode) -> Setting {
+        let mut setting = Setting::new();
+        setting.zscore_mode = mode;
+        setting
+    }
+
+    // 平坦な推移から上方向へ大きく乖離する
+    const SPIKE: [i64; 12] = [0, 1, 0, -1, 0, 1, 0, -1, 0, 1, 0, 10];
+
+    #[test]
+    fn test_zscore_entry_modes() {
+        let t = tickers(&SPIKE);
+        assert_eq!(signal(&setting(ZScoreMode::Reversion), &t), Some(Intent::Entry(Side::Sell)));
+        assert_eq!(signal(&setting(ZScoreMode::Momentum), &t), Some(Intent::Entry(Side::Buy)));
+
+        // 乖離が続いている間は再エントリーしない
+        let mut bids = SPIKE.to_vec();
+        bids.push(10);
+        assert_eq!(signal(&setting(ZScoreMode::Reversion), &tickers(&bids)), None);
+    }
+
+    #[test]
+    fn test_zscore_exit_on_revert() {
+        // 乖離後に平均付近へ戻る
+        let mut bids = SPIKE.to_vec();
+        bids.push(1);
+        let t = tickers(&bids);
+        assert_eq!(signal(&setting(ZScoreMode::Reversion), &t), Some(Intent::Exit(Some(Side::Sell))));
+        assert_eq!(signal(&setting(ZScoreMode::Momentum), &t), Some(Intent::Exit(Some(Side::Buy))));
+    }
+
+    #[test]
+    fn test_zscore_params() {
+        let mut s = Setting::new();
+        let params = ZScoreParams::from_setting(&s).unwrap();
+        assert_eq!(params.field, PriceField::Bid);
+        assert_eq!(params.entry, Decimal::TWO);
+
+        s.zscore_exit = "2".to_string();
+        assert!(ZScoreParams::from_setting(&s).is_none());
+        s.zscore_exit = "0.5".to_string();
+        s.zscore_field = "spread".to_string();
+        assert!(ZScoreParams::from_setting(&s).is_none());
+        assert_eq!(signal(&s, &tickers(&SPIKE)), None);
+    }
+}
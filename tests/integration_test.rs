@@ -95,6 +95,40 @@ fn test_ticker_stats_realistic_diff() {
     assert_eq!(mid, Decimal::from_str("150.010").unwrap());
 }
 
+/// Test SymbolBook keeps diff within a single symbol on a mixed feed
+#[test]
+fn test_symbol_book_mixed_feed_diff() {
+    use chrono::{Duration, Utc};
+
+    let now = Utc::now();
+    let mut book = SymbolBook::default();
+
+    // USDJPY and EURUSD interleaved on the same feed
+    for i in 0..6 {
+        let (symbol, price) = if i % 2 == 0 {
+            ("USDJPY", Decimal::from_str("150.000").unwrap() + Decimal::new(i, 3))
+        } else {
+            ("EURUSD", Decimal::from_str("1.08000").unwrap())
+        };
+        book.push(Ticker {
+            symbol: symbol.to_string(),
+            bid: price,
+            ask: price,
+            recived_at: Some(now + Duration::milliseconds(i * 100)),
+            ..Default::default()
+        });
+    }
+
+    // USDJPY: 150.000 @0ms -> 150.004 @400ms
+    let usdjpy = book.get("USDJPY").unwrap();
+    assert_eq!(usdjpy.len(), 3);
+    assert_eq!(usdjpy.diff(300_000).unwrap(), Decimal::from_str("0.004").unwrap());
+
+    // EURUSD never moved, so its diff stays zero
+    let eurusd = book.get("EURUSD").unwrap();
+    assert_eq!(eurusd.diff(100_000).unwrap(), Decimal::ZERO);
+}
+
 /// Test Setting serialization matches frontend expectations
 #[test]
 fn test_setting_json_contract() {
@@ -142,6 +176,30 @@ fn test_status_json_contract() {
     assert!(json["orders"].is_array());
 }
 
+/// Test MT5/MT4 tick JSON with and without the origin command
+#[test]
+fn test_ticker_command_json_contract() {
+    use signalrs::order_type::{origin::Action, signal::Side};
+
+    let line = r#"{"symbol":"USDJPY","bid":"150.123","ask":"150.125","flag":0,"side":2,
+        "command":{"action":"partial-close","volume":2,"hold_ms":30000,"id":"ea-42"}}"#;
+    let t: Ticker = serde_json::from_str(line).unwrap();
+    let command = t.command.as_ref().unwrap();
+    assert_eq!(command.action, Action::PartialClose);
+    assert_eq!(command.side, None);
+    assert_eq!(command.volume, Some(2));
+    assert_eq!(command.hold_ms, Some(30_000));
+    assert_eq!(command.id.as_deref(), Some("ea-42"));
+    assert_eq!(t.side(), Some(Side::Sell));
+
+    // 従来の整数フラグのみのJSON
+    let line = r#"{"symbol":"USDJPY","bid":"150.123","ask":"150.125","flag":3,"side":1}"#;
+    let t: Ticker = serde_json::from_str(line).unwrap();
+    assert!(t.command.is_none());
+    assert_eq!(t.flag(), 3);
+    assert!(!serde_json::to_string(&t).unwrap().contains("command"));
+}
+
 /// Test consts values
 #[test]
 fn test_consts() {
@@ -191,3 +249,57 @@ fn test_tcp_server_bind() {
         }
     }
 }
+
+/// Test offline pipeline: Recorder -> ReplaySource -> OrderDispatcher::receive -> SymbolBook
+#[test]
+fn test_replay_pipeline() {
+    use chrono::TimeZone;
+    use signalrs::middleware::{
+        recorder::{RecordFormat, Recorder, Rotation},
+        replay::{Pacing, ReplaySource},
+    };
+
+    let dir = std::env::temp_dir().join(format!("signalrs_replay_pipeline_{}", std::process::id()));
+    let _ = std::fs::remove_dir_all(&dir);
+
+    // 10ms間隔で0.01ずつ上昇するtickを記録
+    let base = chrono::Utc.with_ymd_and_hms(2024, 1, 1, 0, 0, 0).unwrap();
+    let path = {
+        let mut recorder = Recorder::new(&dir, RecordFormat::Jsonl, Rotation::Daily);
+        for i in 0..30 {
+            let at = base + chrono::Duration::milliseconds(i * 10);
+            let price = Decimal::from_str("150.00").unwrap() + Decimal::new(i, 2);
+            recorder
+                .record(&Ticker {
+                    symbol: "USDJPY".to_string(),
+                    bid: price,
+                    ask: price,
+                    server_at: Some(at),
+                    recived_at: Some(at),
+                    diff_micros: Some(0),
+                    ..Default::default()
+                })
+                .unwrap();
+        }
+        recorder.path().unwrap().to_path_buf()
+    };
+
+    let (source, rx) = ReplaySource::new(vec![path], Pacing::AsFastAsPossible);
+    let handle = source.start().unwrap();
+    drop(source);
+
+    let dispatcher = OrderDispatcher::new();
+    let setting = Arc::new(RwLock::new(Data::default()));
+    let mut book = SymbolBook::default();
+    for result in rx {
+        dispatcher.receive(setting.clone(), &mut book, result.unwrap());
+    }
+    handle.join().unwrap();
+
+    // 記録時刻のまま再生されるため、100msより前 (180ms時点) との差分は0.11
+    let stats = book.get("USDJPY").unwrap();
+    assert_eq!(stats.len(), 30);
+    assert_eq!(stats.diff(100_000).unwrap(), Decimal::from_str("0.11").unwrap());
+
+    std::fs::remove_dir_all(&dir).unwrap();
+}